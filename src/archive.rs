@@ -0,0 +1,272 @@
+use chrono::{Local, NaiveDate, TimeZone};
+use flate2::read::GzDecoder;
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{self, Read},
+    path::{Component, Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+use tar::EntryType;
+
+/// A file or directory inside an archive.
+#[derive(Debug)]
+pub struct Member {
+    /// The path inside the archive, like `src/main.rs`.
+    pub path: PathBuf,
+    pub is_dir: bool,
+    pub size: u64,
+    pub mode: Option<u32>,
+    pub modified: Option<SystemTime>,
+    pub link_target: Option<PathBuf>,
+}
+
+/// Whether the file is an archive we know how to list, judging by its name.
+pub fn is_archive(path: &Path) -> bool {
+    let name = path.to_string_lossy().to_lowercase();
+
+    [".zip", ".tar", ".tar.gz", ".tgz"]
+        .iter()
+        .any(|ext| name.ends_with(ext))
+}
+
+/// Read the list of members of an archive. Contents are not extracted.
+pub fn read(path: &Path) -> io::Result<Vec<Member>> {
+    let name = path.to_string_lossy().to_lowercase();
+    let file = File::open(path)?;
+
+    let mut members = if name.ends_with(".zip") {
+        read_zip(file)?
+    } else if name.ends_with(".tar") {
+        read_tar(file)?
+    } else {
+        read_tar(GzDecoder::new(file))?
+    };
+
+    // Entries for the archive's root (e.g. `./`) have no path left.
+    members.retain(|member| !member.path.as_os_str().is_empty());
+
+    Ok(members)
+}
+
+/// Keep only the members at the root of the archive, like a directory
+/// listing. Archives don't always have entries for directories, so they're
+/// created from the paths of their contents when needed.
+pub fn top_level(members: Vec<Member>) -> Vec<Member> {
+    let mut root: BTreeMap<PathBuf, Member> = BTreeMap::new();
+
+    for member in members {
+        let mut components = member.path.components();
+        let Some(first) = components.next() else {
+            continue;
+        };
+        let first = PathBuf::from(first.as_os_str());
+
+        if components.next().is_none() {
+            root.insert(first, member);
+        } else {
+            root.entry(first.clone()).or_insert(Member {
+                path: first,
+                is_dir: true,
+                size: 0,
+                mode: None,
+                modified: None,
+                link_target: None,
+            });
+        }
+    }
+
+    root.into_values().collect()
+}
+
+/// Remove `./` prefixes and trailing slashes, which some tools add.
+fn normalize(path: &Path) -> PathBuf {
+    path.components()
+        .filter(|component| !matches!(component, Component::CurDir))
+        .collect()
+}
+
+fn read_zip(file: File) -> io::Result<Vec<Member>> {
+    let mut archive = zip::ZipArchive::new(file)?;
+    let mut members = vec![];
+
+    for index in 0..archive.len() {
+        let file = archive.by_index_raw(index)?;
+
+        // Times are stored without a time zone, so they're assumed to be local.
+        let modified = file.last_modified().and_then(|time| {
+            NaiveDate::from_ymd_opt(time.year().into(), time.month().into(), time.day().into())?
+                .and_hms_opt(
+                    time.hour().into(),
+                    time.minute().into(),
+                    time.second().into(),
+                )
+                .and_then(|time| Local.from_local_datetime(&time).single())
+                .map(SystemTime::from)
+        });
+
+        members.push(Member {
+            path: normalize(Path::new(file.name())),
+            is_dir: file.is_dir(),
+            size: file.size(),
+            mode: file.unix_mode(),
+            modified,
+            link_target: None,
+        });
+    }
+
+    Ok(members)
+}
+
+fn read_tar(reader: impl Read) -> io::Result<Vec<Member>> {
+    let mut archive = tar::Archive::new(reader);
+    let mut members = vec![];
+
+    for entry in archive.entries()? {
+        let entry = entry?;
+        let header = entry.header();
+        let entry_type = header.entry_type();
+
+        // Extended headers describe the following entry, rather than being
+        // entries themselves.
+        if !matches!(
+            entry_type,
+            EntryType::Regular | EntryType::Directory | EntryType::Symlink | EntryType::Link
+        ) {
+            continue;
+        }
+
+        let link_target = match entry_type {
+            EntryType::Symlink => entry.link_name()?.map(|target| target.into_owned()),
+            _ => None,
+        };
+
+        members.push(Member {
+            path: normalize(&entry.path()?),
+            is_dir: entry_type == EntryType::Directory,
+            size: header.size()?,
+            mode: header.mode().ok(),
+            // Times can be larger than `SystemTime` supports, with the GNU
+            // extension for large numbers.
+            modified: header
+                .mtime()
+                .ok()
+                .and_then(|mtime| SystemTime::UNIX_EPOCH.checked_add(Duration::from_secs(mtime))),
+            link_target,
+        });
+    }
+
+    Ok(members)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+    use std::io::{Cursor, Write};
+
+    fn tar(entries: &[(&str, EntryType, u64)]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(vec![]);
+
+        for (path, entry_type, mtime) in entries {
+            let mut header = tar::Header::new_gnu();
+            header.set_entry_type(*entry_type);
+            header.set_size(0);
+            header.set_mode(0o644);
+            header.set_mtime(*mtime);
+            builder.append_data(&mut header, path, io::empty()).unwrap();
+        }
+
+        builder.into_inner().unwrap()
+    }
+
+    #[test]
+    fn reads_tar_members() {
+        let data = tar(&[
+            ("./", EntryType::Directory, 0),
+            ("./src/", EntryType::Directory, 0),
+            ("./src/main.rs", EntryType::Regular, 1_700_000_000),
+        ]);
+        let members = read_tar(Cursor::new(data)).unwrap();
+        let paths: Vec<&Path> = members.iter().map(|member| member.path.as_path()).collect();
+
+        assert_eq!(
+            paths,
+            [Path::new(""), Path::new("src"), Path::new("src/main.rs")]
+        );
+        assert!(members[1].is_dir);
+        assert_eq!(members[2].mode, Some(0o644));
+        assert_eq!(
+            members[2].modified,
+            Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000))
+        );
+    }
+
+    #[test]
+    fn ignores_tar_times_that_overflow() {
+        let data = tar(&[("file", EntryType::Regular, u64::MAX)]);
+        let members = read_tar(Cursor::new(data)).unwrap();
+
+        assert_eq!(members[0].modified, None);
+    }
+
+    #[test]
+    fn rejects_malformed_archives() {
+        assert!(read_tar(Cursor::new(vec![0xff; 1024])).is_err());
+        assert!(zip::ZipArchive::new(Cursor::new(b"PK\x03\x04garbage".to_vec())).is_err());
+    }
+
+    #[test]
+    fn reads_zip_members() {
+        let dir = TempDir::new();
+        let path = dir.path().join("archive.zip");
+        let mut writer = zip::ZipWriter::new(File::create(&path).unwrap());
+        let options = zip::write::SimpleFileOptions::default().unix_permissions(0o755);
+
+        writer.add_directory("bin/", options).unwrap();
+        writer.start_file("bin/run", options).unwrap();
+        writer.write_all(b"#!/bin/sh\n").unwrap();
+        writer.finish().unwrap();
+
+        let members = read(&path).unwrap();
+
+        assert_eq!(members[0].path, Path::new("bin"));
+        assert!(members[0].is_dir);
+        assert_eq!(members[1].path, Path::new("bin/run"));
+        assert_eq!(members[1].size, 10);
+        assert_eq!(members[1].mode.map(|mode| mode & 0o777), Some(0o755));
+    }
+
+    #[test]
+    fn creates_top_level_directories_for_nested_members() {
+        let member = |path: &str, is_dir| Member {
+            path: PathBuf::from(path),
+            is_dir,
+            size: 1,
+            mode: None,
+            modified: None,
+            link_target: None,
+        };
+        let members = top_level(vec![
+            member("README.md", false),
+            member("src/main.rs", false),
+            member("src/lib.rs", false),
+        ]);
+        let paths: Vec<(&Path, bool)> = members
+            .iter()
+            .map(|member| (member.path.as_path(), member.is_dir))
+            .collect();
+
+        assert_eq!(
+            paths,
+            [(Path::new("README.md"), false), (Path::new("src"), true)]
+        );
+    }
+
+    #[test]
+    fn detects_archives_by_name() {
+        assert!(is_archive(Path::new("backup.TAR.GZ")));
+        assert!(is_archive(Path::new("files.zip")));
+        assert!(!is_archive(Path::new("notes.gz")));
+    }
+}
@@ -0,0 +1,198 @@
+use crate::xattr;
+use std::path::Path;
+
+/// The names of the capabilities, indexed by their number.
+const NAMES: [&str; 41] = [
+    "chown",
+    "dac_override",
+    "dac_read_search",
+    "fowner",
+    "fsetid",
+    "kill",
+    "setgid",
+    "setuid",
+    "setpcap",
+    "linux_immutable",
+    "net_bind_service",
+    "net_broadcast",
+    "net_admin",
+    "net_raw",
+    "ipc_lock",
+    "ipc_owner",
+    "sys_module",
+    "sys_rawio",
+    "sys_chroot",
+    "sys_ptrace",
+    "sys_pacct",
+    "sys_admin",
+    "sys_boot",
+    "sys_nice",
+    "sys_resource",
+    "sys_time",
+    "sys_tty_config",
+    "mknod",
+    "lease",
+    "audit_write",
+    "audit_control",
+    "setfcap",
+    "mac_override",
+    "mac_admin",
+    "syslog",
+    "wake_alarm",
+    "block_suspend",
+    "audit_read",
+    "perfmon",
+    "bpf",
+    "checkpoint_restore",
+];
+
+/// Whether the capabilities are raised in the effective set when the file is
+/// executed.
+const VFS_CAP_FLAGS_EFFECTIVE: u32 = 0x0000_0001;
+const VFS_CAP_REVISION_MASK: u32 = 0xff00_0000;
+const VFS_CAP_REVISION_1: u32 = 0x0100_0000;
+
+/// Read the Linux file capabilities of an executable, formatted like `getcap`
+/// does (e.g. `cap_net_bind_service+ep`). Files without capabilities, and
+/// other platforms, have none.
+pub fn get(path: &Path) -> Option<String> {
+    decode(&xattr::get(path, "security.capability")?)
+}
+
+/// Decode the `security.capability` attribute, which has a header with the
+/// revision and flags, followed by the permitted and inheritable sets. The
+/// first revision only has 32 capabilities; later ones have 64.
+fn decode(data: &[u8]) -> Option<String> {
+    let word = |index: usize| -> Option<u32> {
+        let bytes = data.get(index * 4..index * 4 + 4)?;
+        Some(u32::from_le_bytes(bytes.try_into().ok()?))
+    };
+
+    let header = word(0)?;
+    let effective = header & VFS_CAP_FLAGS_EFFECTIVE != 0;
+
+    let (permitted, inheritable) = if header & VFS_CAP_REVISION_MASK == VFS_CAP_REVISION_1 {
+        (u64::from(word(1)?), u64::from(word(2)?))
+    } else {
+        (
+            u64::from(word(1)?) | u64::from(word(3)?) << 32,
+            u64::from(word(2)?) | u64::from(word(4)?) << 32,
+        )
+    };
+
+    // Capabilities with the same flags are grouped, like
+    // `cap_net_admin,cap_net_raw+ep`.
+    let mut groups: Vec<(String, Vec<String>)> = vec![];
+
+    for bit in 0..64 {
+        let is_permitted = permitted & 1 << bit != 0;
+        let is_inheritable = inheritable & 1 << bit != 0;
+
+        if !is_permitted && !is_inheritable {
+            continue;
+        }
+
+        let mut flags = String::new();
+
+        if effective {
+            flags.push('e');
+        }
+
+        if is_inheritable {
+            flags.push('i');
+        }
+
+        if is_permitted {
+            flags.push('p');
+        }
+
+        let name = NAMES
+            .get(bit)
+            .map_or(format!("cap_{bit}"), |name| format!("cap_{name}"));
+
+        match groups
+            .iter_mut()
+            .find(|(group_flags, _)| *group_flags == flags)
+        {
+            Some((_, names)) => names.push(name),
+            None => groups.push((flags, vec![name])),
+        }
+    }
+
+    if groups.is_empty() {
+        return None;
+    }
+
+    let groups: Vec<String> = groups
+        .into_iter()
+        .map(|(flags, names)| format!("{}+{flags}", names.join(",")))
+        .collect();
+
+    Some(groups.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VFS_CAP_REVISION_2: u32 = 0x0200_0000;
+
+    fn attribute(words: &[u32]) -> Vec<u8> {
+        words.iter().flat_map(|word| word.to_le_bytes()).collect()
+    }
+
+    #[test]
+    fn decodes_revision_2() {
+        let data = attribute(&[
+            VFS_CAP_REVISION_2 | VFS_CAP_FLAGS_EFFECTIVE,
+            1 << 10,
+            0,
+            0,
+            0,
+        ]);
+
+        assert_eq!(decode(&data).as_deref(), Some("cap_net_bind_service+ep"));
+    }
+
+    #[test]
+    fn decodes_revision_1() {
+        let data = attribute(&[VFS_CAP_REVISION_1 | VFS_CAP_FLAGS_EFFECTIVE, 1, 0]);
+
+        assert_eq!(decode(&data).as_deref(), Some("cap_chown+ep"));
+    }
+
+    #[test]
+    fn groups_capabilities_by_flags() {
+        let data = attribute(&[
+            VFS_CAP_REVISION_2,
+            1 << 12 | 1 << 13 | 1 << 14,
+            1 << 13,
+            0,
+            0,
+        ]);
+
+        assert_eq!(
+            decode(&data).as_deref(),
+            Some("cap_net_admin,cap_ipc_lock+p cap_net_raw+ip")
+        );
+    }
+
+    #[test]
+    fn decodes_capabilities_above_32() {
+        let data = attribute(&[VFS_CAP_REVISION_2, 0, 0, 1 << 8 | 1 << 13, 0]);
+
+        assert_eq!(
+            decode(&data).as_deref(),
+            Some("cap_checkpoint_restore,cap_45+p")
+        );
+    }
+
+    #[test]
+    fn ignores_empty_and_truncated_attributes() {
+        assert_eq!(decode(&[]), None);
+        assert_eq!(decode(&[0x01, 0x00]), None);
+        assert_eq!(decode(&attribute(&[VFS_CAP_REVISION_2, 0, 0, 0, 0])), None);
+        assert_eq!(decode(&attribute(&[VFS_CAP_REVISION_2, 1, 0])), None);
+        assert_eq!(decode(&attribute(&[VFS_CAP_REVISION_1, 1])), None);
+    }
+}
@@ -0,0 +1,254 @@
+use crossterm::terminal;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, IsTerminal, Write},
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
+const SPINNER: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+
+/// How often the progress indicator is redrawn.
+const REFRESH_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A progress indicator displayed on stderr while directory sizes are being
+/// computed. Nothing is displayed when stderr is not a terminal, or when the
+/// computation is fast enough.
+pub struct Progress {
+    enabled: bool,
+    started_at: Instant,
+    drawn_at: Option<Instant>,
+    frame: usize,
+    files: u64,
+}
+
+impl Progress {
+    pub fn new() -> Self {
+        Self {
+            enabled: io::stderr().is_terminal(),
+            started_at: Instant::now(),
+            drawn_at: None,
+            frame: 0,
+            files: 0,
+        }
+    }
+
+    fn tick(&mut self, dir: &Path) {
+        self.files += 1;
+
+        if !self.enabled || self.started_at.elapsed() < REFRESH_INTERVAL {
+            return;
+        }
+
+        if self
+            .drawn_at
+            .is_some_and(|drawn_at| drawn_at.elapsed() < REFRESH_INTERVAL)
+        {
+            return;
+        }
+
+        self.frame = (self.frame + 1) % SPINNER.len();
+        self.drawn_at = Some(Instant::now());
+
+        let message = format!(
+            "{} Computing sizes ({} files): {}",
+            SPINNER[self.frame],
+            self.files,
+            dir.display()
+        );
+
+        // Long lines would wrap, and couldn't be cleared anymore.
+        let width = terminal::size().map_or(80, |(width, _)| usize::from(width));
+        let message: String = message.chars().take(width.saturating_sub(1)).collect();
+
+        eprint!("\r\x1b[2K{message}");
+        io::stderr().flush().ok();
+    }
+
+    /// Clear the progress indicator, if it was displayed.
+    pub fn finish(&self) {
+        if self.drawn_at.is_some() {
+            eprint!("\r\x1b[2K");
+            io::stderr().flush().ok();
+        }
+    }
+}
+
+/// Compute the total size of a directory's contents, by walking its subtree.
+/// Symlinks are not followed, and entries that can't be read are skipped.
+pub fn dir_size(dir: &Path, progress: &mut Progress) -> u64 {
+    let Ok(children) = fs::read_dir(dir) else {
+        return 0;
+    };
+
+    children
+        .filter_map(Result::ok)
+        .map(|child| {
+            progress.tick(dir);
+
+            match child.metadata() {
+                Ok(metadata) if metadata.is_dir() => dir_size(&child.path(), progress),
+                Ok(metadata) => metadata.len(),
+                Err(_) => 0,
+            }
+        })
+        .sum()
+}
+
+/// The latest modification time of a directory and its subdirectories. It
+/// changes whenever an entry is added, removed, or renamed anywhere in the
+/// subtree, and is cheaper to compute than `dir_size`, as files aren't read.
+fn latest_change(dir: &Path) -> Option<SystemTime> {
+    let modified = fs::metadata(dir)
+        .and_then(|metadata| metadata.modified())
+        .ok()?;
+
+    let Ok(children) = fs::read_dir(dir) else {
+        return Some(modified);
+    };
+
+    children
+        .filter_map(Result::ok)
+        .filter(|child| child.file_type().is_ok_and(|file_type| file_type.is_dir()))
+        .filter_map(|child| latest_change(&child.path()))
+        .chain([modified])
+        .max()
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct CacheEntry {
+    modified: SystemTime,
+    size: u64,
+}
+
+/// Directory sizes computed by previous runs, stored in the cache directory.
+/// Entries are keyed by path, and only used while the latest modification time
+/// of the directory and its subdirectories is unchanged. Files changed in place
+/// don't update it, so `--no-cache` can be used to get fresh sizes.
+#[derive(Debug, Default)]
+pub struct Cache {
+    file: Option<PathBuf>,
+    entries: HashMap<PathBuf, CacheEntry>,
+    changed: bool,
+}
+
+impl Cache {
+    /// Load the cache from disk. A disabled cache computes every size, and
+    /// never writes to disk.
+    pub fn load(enabled: bool) -> Self {
+        let Some(file) = dirs::cache_dir().filter(|_| enabled) else {
+            return Self::default();
+        };
+
+        let file = file.join("ll").join("du.json");
+        let entries = fs::read_to_string(&file)
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default();
+
+        Self {
+            file: Some(file),
+            entries,
+            changed: false,
+        }
+    }
+
+    /// Return the total size of a directory's contents, from the cache when
+    /// possible.
+    pub fn dir_size(&mut self, dir: &Path, progress: &mut Progress) -> u64 {
+        if self.file.is_none() {
+            return dir_size(dir, progress);
+        }
+
+        // The modification time is read before the size, so changes made
+        // while walking the directory invalidate the entry.
+        let (Some(key), Some(modified)) = (fs::canonicalize(dir).ok(), latest_change(dir)) else {
+            return dir_size(dir, progress);
+        };
+
+        if let Some(entry) = self.entries.get(&key) {
+            if entry.modified == modified {
+                return entry.size;
+            }
+        }
+
+        let size = dir_size(dir, progress);
+        self.entries.insert(key, CacheEntry { modified, size });
+        self.changed = true;
+
+        size
+    }
+
+    /// Write the cache to disk, if anything changed. Errors are ignored, as
+    /// the cache is only an optimization.
+    pub fn save(&self) {
+        let Some(file) = &self.file else {
+            return;
+        };
+
+        if !self.changed {
+            return;
+        }
+
+        if let Some(dir) = file.parent() {
+            fs::create_dir_all(dir).ok();
+        }
+
+        if let Ok(contents) = serde_json::to_string(&self.entries) {
+            fs::write(file, contents).ok();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn sums_the_sizes_of_nested_files() {
+        let dir = TempDir::new();
+        dir.write("one", "1");
+        dir.write("a/two", "22");
+        dir.write("a/b/three", "333");
+
+        let mut progress = Progress::new();
+
+        assert_eq!(dir_size(dir.path(), &mut progress), 6);
+        assert_eq!(dir_size(&dir.path().join("missing"), &mut progress), 0);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn invalidates_cached_sizes_when_the_subtree_changes() {
+        let dir = TempDir::new();
+        dir.write("a/b/one", "1");
+
+        // Backdate the directories, so the next change is always newer.
+        let old = SystemTime::UNIX_EPOCH + Duration::from_secs(86_400);
+        for path in ["a", "a/b"] {
+            fs::File::open(dir.path().join(path))
+                .and_then(|file| file.set_modified(old))
+                .unwrap();
+        }
+
+        let mut cache = Cache {
+            file: Some(dir.path().join("du.json")),
+            ..Cache::default()
+        };
+        let mut progress = Progress::new();
+        let path = dir.path().join("a");
+
+        assert_eq!(cache.dir_size(&path, &mut progress), 1);
+
+        // Cached sizes are used while nothing changes.
+        let key = fs::canonicalize(&path).unwrap();
+        cache.entries.get_mut(&key).unwrap().size = 42;
+        assert_eq!(cache.dir_size(&path, &mut progress), 42);
+
+        dir.write("a/b/two", "22");
+        assert_eq!(cache.dir_size(&path, &mut progress), 3);
+    }
+}
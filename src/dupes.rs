@@ -0,0 +1,34 @@
+use crate::hash::{self, Algorithm};
+use std::{collections::HashMap, path::PathBuf};
+
+/// Group the given files (along with their sizes) by content, returning only
+/// the groups with more than one file. Only files with the same size are
+/// hashed, so most files are never read.
+pub fn find(files: Vec<(PathBuf, u64)>) -> Vec<Vec<PathBuf>> {
+    let mut by_size: HashMap<u64, Vec<PathBuf>> = HashMap::new();
+
+    for (path, size) in files {
+        by_size.entry(size).or_default().push(path);
+    }
+
+    let candidates: Vec<PathBuf> = by_size
+        .into_values()
+        .filter(|paths| paths.len() > 1)
+        .flatten()
+        .collect();
+    let hashes = hash::files(&candidates, Algorithm::Sha256);
+
+    // Files that can't be read (e.g. because of permissions) are skipped.
+    let mut by_hash: HashMap<String, Vec<PathBuf>> = HashMap::new();
+
+    for (path, hash) in candidates.into_iter().zip(hashes) {
+        if let Some(hash) = hash {
+            by_hash.entry(hash).or_default().push(path);
+        }
+    }
+
+    by_hash
+        .into_values()
+        .filter(|paths| paths.len() > 1)
+        .collect()
+}
@@ -0,0 +1,152 @@
+use std::path::Path;
+
+/// Read the file flags that restrict what can be done with an entry, like
+/// `chattr` attributes on Linux (e.g. `ia` for immutable and append-only), or
+/// `chflags` flags on macOS and BSD (e.g. `uchg,hidden`). Returns `None` when
+/// they can't be read, or the platform has none.
+pub fn get(path: &Path) -> Option<String> {
+    sys::get(path)
+}
+
+/// Whether an entry has the `hidden` flag, which Finder uses to hide entries
+/// the same way dotfiles are. Only macOS and BSD have it.
+pub fn is_hidden(path: &Path) -> bool {
+    sys::is_hidden(path)
+}
+
+#[cfg(target_os = "linux")]
+mod sys {
+    use std::{fs::OpenOptions, os::fd::AsRawFd, os::unix::fs::OpenOptionsExt, path::Path};
+
+    /// The letters used by `lsattr`, for the attributes users set with
+    /// `chattr`. Attributes managed by the filesystem itself (e.g. extents)
+    /// are left out, as most files have them.
+    const LETTERS: [(libc::c_int, char); 14] = [
+        (0x0000_0001, 's'), // Secure deletion.
+        (0x0000_0002, 'u'), // Undeletable.
+        (0x0000_0004, 'c'), // Compressed.
+        (0x0000_0008, 'S'), // Synchronous updates.
+        (0x0000_0010, 'i'), // Immutable.
+        (0x0000_0020, 'a'), // Append only.
+        (0x0000_0040, 'd'), // No dump.
+        (0x0000_0080, 'A'), // No access time updates.
+        (0x0000_4000, 'j'), // Data journaling.
+        (0x0000_8000, 't'), // No tail merging.
+        (0x0001_0000, 'D'), // Synchronous directory updates.
+        (0x0002_0000, 'T'), // Top of directory hierarchies.
+        (0x0080_0000, 'C'), // No copy on write.
+        (0x2000_0000, 'P'), // Project hierarchy.
+    ];
+
+    pub fn get(path: &Path) -> Option<String> {
+        // Symlinks are not followed, as their own attributes can't be read.
+        // Opening without blocking avoids hanging on FIFOs.
+        let file = OpenOptions::new()
+            .read(true)
+            .custom_flags(libc::O_NOFOLLOW | libc::O_NONBLOCK)
+            .open(path)
+            .ok()?;
+
+        let mut flags: libc::c_int = 0;
+
+        // SAFETY: the ioctl writes a single `int` to the pointer it's given.
+        let code = unsafe { libc::ioctl(file.as_raw_fd(), libc::FS_IOC_GETFLAGS, &mut flags) };
+
+        if code != 0 {
+            return None;
+        }
+
+        Some(
+            LETTERS
+                .iter()
+                .filter(|(flag, _)| flags & flag != 0)
+                .map(|(_, letter)| letter)
+                .collect(),
+        )
+    }
+
+    pub fn is_hidden(_path: &Path) -> bool {
+        false
+    }
+}
+
+#[cfg(any(
+    target_os = "macos",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd",
+    target_os = "dragonfly"
+))]
+mod sys {
+    use std::{ffi::CString, mem::MaybeUninit, os::unix::ffi::OsStrExt, path::Path};
+
+    const UF_HIDDEN: u32 = 0x0000_8000;
+
+    /// The names used by `ls -lO` and `chflags`, for the flags shared by
+    /// macOS and the BSDs.
+    const NAMES: [(u32, &str); 9] = [
+        (0x0000_0001, "nodump"),
+        (0x0000_0002, "uchg"),
+        (0x0000_0004, "uappnd"),
+        (0x0000_0008, "opaque"),
+        (UF_HIDDEN, "hidden"),
+        (0x0001_0000, "arch"),
+        (0x0002_0000, "schg"),
+        (0x0004_0000, "sappnd"),
+        (0x0010_0000, "sunlnk"),
+    ];
+
+    pub fn get(path: &Path) -> Option<String> {
+        let flags = read_flags(path)?;
+
+        let names: Vec<&str> = NAMES
+            .iter()
+            .filter(|(flag, _)| flags & flag != 0)
+            .map(|(_, name)| *name)
+            .collect();
+
+        Some(names.join(","))
+    }
+
+    pub fn is_hidden(path: &Path) -> bool {
+        read_flags(path).is_some_and(|flags| flags & UF_HIDDEN != 0)
+    }
+
+    /// The flags of an entry, without following symlinks.
+    fn read_flags(path: &Path) -> Option<u32> {
+        let path = CString::new(path.as_os_str().as_bytes()).ok()?;
+        let mut stat = MaybeUninit::<libc::stat>::uninit();
+
+        // SAFETY: `stat` is only read after the call succeeds, which means it
+        // was filled in.
+        let stat = unsafe {
+            if libc::lstat(path.as_ptr(), stat.as_mut_ptr()) != 0 {
+                return None;
+            }
+
+            stat.assume_init()
+        };
+
+        Some(stat.st_flags)
+    }
+}
+
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "macos",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd",
+    target_os = "dragonfly"
+)))]
+mod sys {
+    use std::path::Path;
+
+    pub fn get(_path: &Path) -> Option<String> {
+        None
+    }
+
+    pub fn is_hidden(_path: &Path) -> bool {
+        false
+    }
+}
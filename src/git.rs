@@ -0,0 +1,269 @@
+use ignore::{
+    gitignore::{Gitignore, GitignoreBuilder},
+    Match,
+};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// The git status of an entry. Variants are ordered by relevance, so
+/// directories can display the most relevant status of their contents.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Status {
+    Unmodified,
+    Ignored,
+    Untracked,
+    Staged,
+    Modified,
+}
+
+impl Status {
+    pub fn marker(self) -> &'static str {
+        match self {
+            Status::Unmodified => " ",
+            Status::Ignored => "!",
+            Status::Untracked => "?",
+            Status::Staged => "S",
+            Status::Modified => "M",
+        }
+    }
+
+    pub fn color_type(self) -> &'static str {
+        match self {
+            Status::Unmodified => "file",
+            Status::Ignored => "git_ignored",
+            Status::Untracked => "git_untracked",
+            Status::Staged => "git_staged",
+            Status::Modified => "git_modified",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Repository {
+    root: PathBuf,
+    /// The status of each entry listed by git. Untracked and ignored
+    /// directories are listed as a whole, rather than their contents.
+    statuses: HashMap<PathBuf, Status>,
+    /// The most relevant status of each directory's contents. Ignored entries
+    /// are left out, so directories with ignored files inside (e.g.
+    /// `.DS_Store`) still look clean.
+    dir_statuses: HashMap<PathBuf, Status>,
+}
+
+impl Repository {
+    /// Find the repository that contains `dir` and load the status of its
+    /// entries. Returns `None` when `dir` is not inside a git repository, or
+    /// when git is not available.
+    pub fn discover(dir: &Path) -> Option<Self> {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(["rev-parse", "--show-toplevel"])
+            .output()
+            .ok()?;
+
+        if !output.status.success() {
+            return None;
+        }
+
+        let root = PathBuf::from(String::from_utf8_lossy(&output.stdout).trim());
+
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(&root)
+            .args(["status", "--porcelain=v1", "-z", "--ignored"])
+            .output()
+            .ok()?;
+
+        if !output.status.success() {
+            return None;
+        }
+
+        Some(Self::parse(root, &String::from_utf8_lossy(&output.stdout)))
+    }
+
+    /// Build the repository from the output of `git status --porcelain=v1 -z
+    /// --ignored`, run at its root.
+    fn parse(root: PathBuf, output: &str) -> Self {
+        let mut records = output.split('\0');
+        let mut statuses = HashMap::new();
+        let mut dir_statuses = HashMap::new();
+
+        while let Some(record) = records.next() {
+            if record.len() < 4 {
+                continue;
+            }
+
+            let (code, path) = record.split_at(3);
+            let mut code = code.chars();
+            let index = code.next().unwrap_or(' ');
+            let worktree = code.next().unwrap_or(' ');
+
+            // Renames and copies are followed by the original path, which we
+            // don't need.
+            if matches!(index, 'R' | 'C') {
+                records.next();
+            }
+
+            let status = match (index, worktree) {
+                ('?', _) => Status::Untracked,
+                ('!', _) => Status::Ignored,
+                (_, ' ') => Status::Staged,
+                _ => Status::Modified,
+            };
+
+            let path = root.join(path.trim_end_matches('/'));
+
+            if status != Status::Ignored {
+                for dir in path.ancestors().skip(1) {
+                    let dir_status = dir_statuses.entry(dir.to_path_buf()).or_insert(status);
+                    *dir_status = status.max(*dir_status);
+
+                    if dir == root {
+                        break;
+                    }
+                }
+            }
+
+            statuses.insert(path, status);
+        }
+
+        Self {
+            root,
+            statuses,
+            dir_statuses,
+        }
+    }
+
+    /// Return the status for the given path. Directories get the most relevant
+    /// status of their contents, and entries inside untracked or ignored
+    /// directories inherit the directory's status.
+    pub fn status(&self, path: &Path) -> Status {
+        let Some(path) = absolute_path(path) else {
+            return Status::Unmodified;
+        };
+
+        if let Some(status) = self
+            .statuses
+            .get(&path)
+            .or_else(|| self.dir_statuses.get(&path))
+        {
+            return *status;
+        }
+
+        path.ancestors()
+            .skip(1)
+            .take_while(|dir| dir.starts_with(&self.root))
+            .find_map(|dir| self.statuses.get(dir))
+            .copied()
+            .unwrap_or(Status::Unmodified)
+    }
+}
+
+/// The ignore rules that apply to the entries of a directory: `.gitignore`
+/// files from the directory up to the repository root, `.git/info/exclude`,
+/// and the global gitignore.
+#[derive(Debug)]
+pub struct Ignore {
+    // Sorted by precedence, so the first match wins.
+    matchers: Vec<Gitignore>,
+}
+
+impl Ignore {
+    /// Load the ignore rules for `dir`. Returns `None` when `dir` is not inside
+    /// a git repository.
+    pub fn load(dir: &Path) -> Option<Self> {
+        let dir = fs::canonicalize(dir).ok()?;
+        let root = dir.ancestors().find(|dir| dir.join(".git").exists())?;
+        let mut matchers = vec![];
+
+        for dir in dir.ancestors() {
+            let path = dir.join(".gitignore");
+
+            if path.is_file() {
+                matchers.push(Gitignore::new(path).0);
+            }
+
+            if dir == root {
+                break;
+            }
+        }
+
+        let mut builder = GitignoreBuilder::new(root);
+        builder.add(root.join(".git").join("info").join("exclude"));
+        matchers.extend(builder.build().ok());
+        matchers.push(GitignoreBuilder::new(root).build_global().0);
+
+        Some(Self { matchers })
+    }
+
+    /// Return whether the given entry is ignored, either directly or because
+    /// one of its parents is.
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        let Some(path) = absolute_path(path) else {
+            return false;
+        };
+
+        for matcher in &self.matchers {
+            if !path.starts_with(matcher.path()) {
+                continue;
+            }
+
+            match matcher.matched_path_or_any_parents(&path, is_dir) {
+                Match::Ignore(_) => return true,
+                Match::Whitelist(_) => return false,
+                Match::None => {}
+            }
+        }
+
+        false
+    }
+}
+
+// Only the parent is canonicalized, so symlinks (even dead ones) are matched
+// against their own path, not their target's.
+fn absolute_path(path: &Path) -> Option<PathBuf> {
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+
+    Some(fs::canonicalize(parent).ok()?.join(path.file_name()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn resolves_the_status_of_entries_and_directories() {
+        let dir = TempDir::new();
+
+        for subdir in ["src", "docs", "target/debug", "vendor/lib"] {
+            fs::create_dir_all(dir.path().join(subdir)).unwrap();
+        }
+
+        let root = fs::canonicalize(dir.path()).unwrap();
+        let repo = Repository::parse(
+            root.clone(),
+            " M src/main.rs\0!! src/.DS_Store\0!! docs/.DS_Store\0!! target/\0?? vendor/\0\
+             R  new.rs\0old.rs\0A  staged.rs\0",
+        );
+
+        assert_eq!(repo.status(&root.join("src/main.rs")), Status::Modified);
+        assert_eq!(repo.status(&root.join("src")), Status::Modified);
+        assert_eq!(repo.status(&root.join("docs")), Status::Unmodified);
+        assert_eq!(repo.status(&root.join("docs/.DS_Store")), Status::Ignored);
+        assert_eq!(repo.status(&root.join("target")), Status::Ignored);
+        assert_eq!(repo.status(&root.join("target/debug")), Status::Ignored);
+        assert_eq!(repo.status(&root.join("vendor/lib")), Status::Untracked);
+        assert_eq!(repo.status(&root.join("new.rs")), Status::Staged);
+        assert_eq!(repo.status(&root.join("old.rs")), Status::Unmodified);
+        assert_eq!(repo.status(&root.join("staged.rs")), Status::Staged);
+    }
+}
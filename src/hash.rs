@@ -0,0 +1,94 @@
+use clap::ValueEnum;
+use md5::Md5;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use std::{
+    fs::File,
+    io,
+    path::{Path, PathBuf},
+    thread,
+};
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum Algorithm {
+    Md5,
+    Sha1,
+    Sha256,
+}
+
+/// Compute the digest of a file's contents, as a lowercase hex string.
+pub fn file(path: &Path, algorithm: Algorithm) -> io::Result<String> {
+    let mut file = File::open(path)?;
+
+    let digest = match algorithm {
+        Algorithm::Md5 => hash_reader::<Md5>(&mut file)?,
+        Algorithm::Sha1 => hash_reader::<Sha1>(&mut file)?,
+        Algorithm::Sha256 => hash_reader::<Sha256>(&mut file)?,
+    };
+
+    Ok(digest.iter().map(|byte| format!("{byte:02x}")).collect())
+}
+
+/// Compute the digests of many files, spreading the work across threads.
+/// Files that can't be read (e.g. because of permissions) get `None`.
+pub fn files(paths: &[PathBuf], algorithm: Algorithm) -> Vec<Option<String>> {
+    let threads = thread::available_parallelism().map_or(1, usize::from);
+    let chunk_size = paths.len().div_ceil(threads).max(1);
+
+    thread::scope(|scope| {
+        let handles: Vec<_> = paths
+            .chunks(chunk_size)
+            .map(|chunk| {
+                scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .map(|path| file(path, algorithm).ok())
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().expect("Couldn't hash files"))
+            .collect()
+    })
+}
+
+fn hash_reader<D: Digest + io::Write>(reader: &mut impl io::Read) -> io::Result<Vec<u8>> {
+    let mut hasher = D::new();
+    io::copy(reader, &mut hasher)?;
+
+    Ok(hasher.finalize().to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn hashes_files() {
+        let dir = TempDir::new();
+        let path = dir.write("abc.txt", "abc");
+
+        assert_eq!(
+            file(&path, Algorithm::Md5).unwrap(),
+            "900150983cd24fb0d6963f7d28e17f72"
+        );
+        assert_eq!(
+            file(&path, Algorithm::Sha1).unwrap(),
+            "a9993e364706816aba3e25717850c26c9cd0d89d"
+        );
+        assert_eq!(
+            file(&path, Algorithm::Sha256).unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+
+        let missing = dir.path().join("missing.txt");
+        assert_eq!(
+            files(&[path, missing], Algorithm::Md5),
+            [Some("900150983cd24fb0d6963f7d28e17f72".to_string()), None]
+        );
+    }
+}
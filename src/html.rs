@@ -0,0 +1,243 @@
+use crossterm::style::Color;
+use std::io::{self, Write};
+
+/// An entry in the HTML listing.
+pub struct Item {
+    pub name: String,
+    /// The link to the entry, relative to the listed directory.
+    pub href: String,
+    /// The NerdFonts glyph, when icons are enabled.
+    pub icon: Option<String>,
+    /// The CSS class with the entry's color (e.g. `dir` or `executable_file`).
+    pub class: String,
+    pub size: Option<String>,
+    pub modified: Option<String>,
+}
+
+/// Write a standalone HTML page listing the given items. `styles` is a list of
+/// CSS classes and their declarations (e.g. `("dir", "color: #0000ff")`).
+pub fn render(
+    out: &mut impl Write,
+    title: &str,
+    items: &[Item],
+    styles: &[(String, String)],
+) -> io::Result<()> {
+    writeln!(out, "<!DOCTYPE html>")?;
+    writeln!(out, "<html>")?;
+    writeln!(out, "<head>")?;
+    writeln!(out, "<meta charset=\"utf-8\">")?;
+    writeln!(out, "<title>{}</title>", escape(title))?;
+    writeln!(out, "<style>")?;
+    writeln!(
+        out,
+        "body {{ background: #1e1e1e; color: #e5e5e5; font-family: ui-monospace, monospace; }}"
+    )?;
+    writeln!(out, "a {{ color: inherit; text-decoration: none; }}")?;
+    writeln!(out, "a:hover {{ text-decoration: underline; }}")?;
+    writeln!(out, "td {{ padding: 0 1em 0 0; white-space: nowrap; }}")?;
+    writeln!(out, "td.size {{ text-align: right; }}")?;
+    writeln!(
+        out,
+        ".icon {{ font-family: \"Symbols Nerd Font\", \"Symbols Nerd Font Mono\", monospace; }}"
+    )?;
+
+    for (class, declarations) in styles {
+        writeln!(out, ".{} {{ {declarations} }}", escape_class(class))?;
+    }
+
+    writeln!(out, "</style>")?;
+    writeln!(out, "</head>")?;
+    writeln!(out, "<body>")?;
+    writeln!(out, "<h1>{}</h1>", escape(title))?;
+    writeln!(out, "<table>")?;
+
+    for item in items {
+        let icon = item.icon.as_deref().map_or(String::new(), |icon| {
+            format!("<span class=\"icon\">{}</span> ", encode_codepoints(icon))
+        });
+
+        writeln!(
+            out,
+            "<tr><td class=\"{}\">{icon}<a href=\"{}\">{}</a></td><td class=\"size\">{}</td><td>{}</td></tr>",
+            escape(&item.class),
+            escape(&item.href),
+            escape(&item.name),
+            item.size.as_deref().unwrap_or("-"),
+            item.modified.as_deref().unwrap_or_default()
+        )?;
+    }
+
+    writeln!(out, "</table>")?;
+    writeln!(out, "</body>")?;
+    writeln!(out, "</html>")
+}
+
+/// Percent-encode a path for use in a link. Slashes are kept, so links to
+/// directories can end with one.
+pub fn encode_href(path: &str) -> String {
+    path.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                char::from(byte).to_string()
+            }
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+/// The CSS equivalent of a terminal color. Named colors use the common xterm
+/// palette.
+pub fn css_color(color: Color) -> String {
+    let (r, g, b) = match color {
+        Color::Rgb { r, g, b } => (r, g, b),
+        Color::AnsiValue(index) => ansi_rgb(index),
+        Color::Black => (0x00, 0x00, 0x00),
+        Color::DarkRed => (0xcd, 0x00, 0x00),
+        Color::DarkGreen => (0x00, 0xcd, 0x00),
+        Color::DarkYellow => (0xcd, 0xcd, 0x00),
+        Color::DarkBlue => (0x00, 0x00, 0xee),
+        Color::DarkMagenta => (0xcd, 0x00, 0xcd),
+        Color::DarkCyan => (0x00, 0xcd, 0xcd),
+        Color::Grey => (0xe5, 0xe5, 0xe5),
+        Color::DarkGrey => (0x7f, 0x7f, 0x7f),
+        Color::Red => (0xff, 0x00, 0x00),
+        Color::Green => (0x00, 0xff, 0x00),
+        Color::Yellow => (0xff, 0xff, 0x00),
+        Color::Blue => (0x5c, 0x5c, 0xff),
+        Color::Magenta => (0xff, 0x00, 0xff),
+        Color::Cyan => (0x00, 0xff, 0xff),
+        Color::White | Color::Reset => (0xff, 0xff, 0xff),
+    };
+
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+/// Convert a 256-color index to RGB. The first 16 colors are the named ones,
+/// followed by a 6x6x6 color cube and a grayscale ramp.
+fn ansi_rgb(index: u8) -> (u8, u8, u8) {
+    const NAMED: [Color; 16] = [
+        Color::Black,
+        Color::DarkRed,
+        Color::DarkGreen,
+        Color::DarkYellow,
+        Color::DarkBlue,
+        Color::DarkMagenta,
+        Color::DarkCyan,
+        Color::Grey,
+        Color::DarkGrey,
+        Color::Red,
+        Color::Green,
+        Color::Yellow,
+        Color::Blue,
+        Color::Magenta,
+        Color::Cyan,
+        Color::White,
+    ];
+
+    match index {
+        0..=15 => {
+            let hex = css_color(NAMED[usize::from(index)]);
+            let channel = |start: usize| u8::from_str_radix(&hex[start..start + 2], 16).unwrap();
+
+            (channel(1), channel(3), channel(5))
+        }
+        16..=231 => {
+            let level = |value: u8| if value == 0 { 0 } else { 55 + value * 40 };
+            let index = index - 16;
+
+            (level(index / 36), level(index / 6 % 6), level(index % 6))
+        }
+        _ => {
+            let level = 8 + (index - 232) * 10;
+
+            (level, level, level)
+        }
+    }
+}
+
+/// Icons are written as character references (e.g. `&#xe7a8;`), so the page
+/// stays readable in editors without NerdFonts.
+fn encode_codepoints(input: &str) -> String {
+    input
+        .chars()
+        .map(|char| {
+            if char.is_ascii() {
+                escape(&char.to_string())
+            } else {
+                format!("&#x{:x};", u32::from(char))
+            }
+        })
+        .collect()
+}
+
+/// Escape a class name for a CSS selector, as names from color rules can be
+/// globs (e.g. `*.rs`).
+fn escape_class(class: &str) -> String {
+    class
+        .chars()
+        .enumerate()
+        .map(|(index, char)| match char {
+            '0'..='9' if index == 0 => format!("\\{:x} ", u32::from(char)),
+            'A'..='Z' | 'a'..='z' | '0'..='9' | '-' | '_' => char.to_string(),
+            _ if !char.is_ascii() => char.to_string(),
+            _ => format!("\\{char}"),
+        })
+        .collect()
+}
+
+fn escape(input: &str) -> String {
+    input
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_hrefs() {
+        assert_eq!(encode_href("src/main.rs"), "src/main.rs");
+        assert_eq!(encode_href("my file#1?.txt"), "my%20file%231%3F.txt");
+        assert_eq!(encode_href("café/"), "caf%C3%A9/");
+    }
+
+    #[test]
+    fn converts_colors_to_css() {
+        assert_eq!(css_color(Color::Rgb { r: 1, g: 2, b: 255 }), "#0102ff");
+        assert_eq!(css_color(Color::DarkRed), "#cd0000");
+        assert_eq!(css_color(Color::Reset), "#ffffff");
+        assert_eq!(css_color(Color::AnsiValue(1)), "#cd0000");
+    }
+
+    #[test]
+    fn converts_256_colors_to_rgb() {
+        assert_eq!(ansi_rgb(9), (0xff, 0x00, 0x00));
+        assert_eq!(ansi_rgb(16), (0, 0, 0));
+        assert_eq!(ansi_rgb(21), (0, 0, 255));
+        assert_eq!(ansi_rgb(196), (255, 0, 0));
+        assert_eq!(ansi_rgb(231), (255, 255, 255));
+        assert_eq!(ansi_rgb(232), (8, 8, 8));
+        assert_eq!(ansi_rgb(255), (238, 238, 238));
+    }
+
+    #[test]
+    fn escapes_html() {
+        assert_eq!(
+            escape(r#"<a href="x">Tom & Jerry's</a>"#),
+            "&lt;a href=&quot;x&quot;&gt;Tom &amp; Jerry&#39;s&lt;/a&gt;"
+        );
+        assert_eq!(encode_codepoints("\u{e7a8} <"), "&#xe7a8; &lt;");
+    }
+
+    #[test]
+    fn escapes_css_class_names() {
+        assert_eq!(escape_class("file_name-1"), "file_name-1");
+        assert_eq!(escape_class("*.rs"), "\\*\\.rs");
+        assert_eq!(escape_class("1password"), "\\31 password");
+        assert_eq!(escape_class("café"), "café");
+    }
+}
@@ -0,0 +1,119 @@
+use crate::Style;
+
+/// Parse a `LS_COLORS` value (as generated by `dircolors`) into a list of keys
+/// and their styles. Keys are either entry types (e.g. `di` or `ex`) or glob
+/// patterns (e.g. `*.tar`), in the order they were defined.
+pub fn parse(value: &str) -> Vec<(String, Style)> {
+    value
+        .split(':')
+        .filter_map(|entry| entry.split_once('='))
+        // `ln=target` means "use the target's color", which isn't supported.
+        .filter(|(_, codes)| !codes.is_empty() && *codes != "target")
+        .map(|(key, codes)| (key.to_string(), parse_style(codes)))
+        .collect()
+}
+
+/// Convert SGR codes (e.g. `01;34`) into a style. Background colors are
+/// ignored, as only the foreground color can be configured.
+fn parse_style(codes: &str) -> Style {
+    const COLORS: [&str; 8] = [
+        "black",
+        "darkred",
+        "darkgreen",
+        "darkyellow",
+        "darkblue",
+        "darkmagenta",
+        "darkcyan",
+        "grey",
+    ];
+    const BRIGHT_COLORS: [&str; 8] = [
+        "darkgrey", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+    ];
+
+    let mut style = Style::default();
+    let mut codes = codes.split(';').map(|code| code.parse::<u8>().unwrap_or(0));
+
+    while let Some(code) = codes.next() {
+        match code {
+            0 => style = Style::default(),
+            1 => style.bold = true,
+            2 => style.dim = true,
+            3 => style.italic = true,
+            4 => style.underline = true,
+            30..=37 => style.color = Some(COLORS[usize::from(code - 30)].to_string()),
+            90..=97 => style.color = Some(BRIGHT_COLORS[usize::from(code - 90)].to_string()),
+            38 | 48 => {
+                let color = match codes.next() {
+                    Some(5) => codes.next().map(|index| index.to_string()),
+                    Some(2) => {
+                        let (r, g, b) = (codes.next(), codes.next(), codes.next());
+                        Some(format!(
+                            "rgb({}, {}, {})",
+                            r.unwrap_or(0),
+                            g.unwrap_or(0),
+                            b.unwrap_or(0)
+                        ))
+                    }
+                    _ => None,
+                };
+
+                if code == 38 {
+                    style.color = color;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    style
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_keys_in_order() {
+        let keys: Vec<String> = parse("di=01;34:ln=target:*.tar=31:fi=:ex=01;32")
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect();
+
+        assert_eq!(keys, ["di", "*.tar", "ex"]);
+    }
+
+    #[test]
+    fn parses_attributes_and_colors() {
+        let style = parse_style("01;04;34");
+
+        assert_eq!(style.color.as_deref(), Some("darkblue"));
+        assert!(style.bold && style.underline && !style.italic && !style.dim);
+        assert_eq!(parse_style("92").color.as_deref(), Some("green"));
+    }
+
+    #[test]
+    fn parses_extended_colors() {
+        assert_eq!(parse_style("38;5;208").color.as_deref(), Some("208"));
+        assert_eq!(
+            parse_style("38;2;255;136;0").color.as_deref(),
+            Some("rgb(255, 136, 0)")
+        );
+
+        // Background colors are skipped, along with their arguments.
+        assert_eq!(
+            parse_style("48;5;1;33").color.as_deref(),
+            Some("darkyellow")
+        );
+    }
+
+    #[test]
+    fn ignores_malformed_codes() {
+        assert_eq!(
+            parse_style("38;2;255").color.as_deref(),
+            Some("rgb(255, 0, 0)")
+        );
+        assert_eq!(parse_style("38").color, None);
+        assert_eq!(parse_style("foo;999").color, None);
+        assert!(parse("garbage:=:di").is_empty());
+    }
+}
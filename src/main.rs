@@ -1,48 +1,221 @@
-use clap::Parser;
+use chrono::{
+    format::{Item, StrftimeItems},
+    DateTime, Duration, Local, NaiveDate, NaiveTime,
+};
+use clap::{builder::PossibleValuesParser, CommandFactory, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
 use crossterm::{
+    cursor, execute,
     style::{Color, Stylize},
-    terminal,
+    terminal::{self, ClearType},
 };
 use glob::glob;
+use indexmap::IndexMap;
 use regex::Regex;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::{
-    cmp::max,
+    cmp::{max, Ordering},
     collections::HashMap,
     fs::{self, Metadata},
+    io::{self, IsTerminal, Write},
     path::{Path, PathBuf, MAIN_SEPARATOR},
     process,
+    sync::LazyLock,
+    thread,
+    time::{self, SystemTime},
 };
 use thiserror::Error;
 
+mod archive;
+mod caps;
+mod du;
+mod dupes;
+mod flags;
+mod git;
+mod hash;
+mod html;
+mod ls_colors;
+mod media;
+mod mime;
+mod pager;
+mod template;
+#[cfg(test)]
+mod testing;
+mod thumbnails;
+mod users;
+mod watch;
+mod xattr;
+
 #[cfg(unix)]
-use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::os::unix::fs::{FileTypeExt, MetadataExt, PermissionsExt};
 
 #[cfg(windows)]
 use std::os::windows::fs::MetadataExt;
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Default)]
 struct OptionalConfig {
+    /// The file this configuration was read from, used in warnings.
+    #[serde(skip)]
+    path: Option<PathBuf>,
+    theme: Option<String>,
+    ls_colors: Option<bool>,
+    icons: Option<bool>,
+    hyperlink: Option<bool>,
+    pager: Option<bool>,
+    summary: Option<bool>,
+    sizes: Option<bool>,
+    binary_sizes: Option<bool>,
+    highlight_world_writable: Option<bool>,
+    detect_binary_files: Option<bool>,
+    git_ignore: Option<bool>,
+    column_gap: Option<usize>,
+    padding: Option<char>,
+    color_rules: Option<IndexMap<String, ColorValue>>,
+    time_format: Option<String>,
+    thousands_separator: Option<String>,
     aliases: Option<HashMap<String, String>>,
     folders: Option<HashMap<String, String>>,
     files: Option<HashMap<String, String>>,
-    colors: Option<HashMap<String, String>>,
+    kinds: Option<HashMap<String, String>>,
+    content_types: Option<HashMap<String, String>>,
+    colors: Option<HashMap<String, ColorValue>>,
     ignore: Option<HashMap<String, Vec<String>>>,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
 struct Config {
+    /// Whether the output must be colored; resolved at runtime from the
+    /// `--color` flag and the environment.
+    #[serde(skip)]
+    colorize: bool,
+    /// Glob patterns matched against entry names, and the key of their color.
+    /// Rules are matched in order, and the first match wins.
+    #[serde(skip)]
+    color_rules: Vec<(glob::Pattern, String)>,
+    /// The graphics protocol used to render image thumbnails, when enabled and
+    /// supported by the terminal.
+    #[serde(skip)]
+    thumbnails: Option<thumbnails::Protocol>,
+    /// Whether directories show how many entries they contain.
+    #[serde(skip)]
+    count: bool,
+    /// Whether entries are displayed with their path relative to the listed
+    /// directory, rather than only their name.
+    #[serde(skip)]
+    full_paths: bool,
+    /// Whether names are followed by a character telling their type, like
+    /// `*` for executables.
+    #[serde(skip)]
+    classify: bool,
+    /// Whether names are printed as they are, rather than quoted when they
+    /// have spaces or control characters.
+    #[serde(skip)]
+    literal: bool,
+    /// Whether sizes are displayed in bytes, rather than humanized.
+    #[serde(skip)]
+    bytes: bool,
+    /// Whether file sizes are the space allocated on disk, rather than the
+    /// apparent size.
+    #[serde(skip)]
+    allocated: bool,
+    /// Whether the space allocated on disk is displayed next to file sizes.
+    #[serde(skip)]
+    on_disk: bool,
+    ls_colors: bool,
+    icons: bool,
+    hyperlink: bool,
+    pager: bool,
+    summary: bool,
+    sizes: bool,
+    binary_sizes: bool,
+    highlight_world_writable: bool,
+    detect_binary_files: bool,
+    git_ignore: bool,
+    column_gap: usize,
+    padding: char,
+    time_format: Option<String>,
+    thousands_separator: Option<String>,
     aliases: HashMap<String, String>,
     folders: HashMap<String, String>,
     files: HashMap<String, String>,
-    colors: HashMap<String, String>,
+    kinds: HashMap<String, String>,
+    content_types: HashMap<String, String>,
+    colors: HashMap<String, ColorValue>,
     ignore: HashMap<String, Vec<String>>,
 }
 
+/// A color from the `colors` configuration. Can be a color name (or value,
+/// like `#ff8800`), a 256-color index, or a table with style attributes (e.g.
+/// `{ color = "green", bold = true }`).
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(untagged)]
+enum ColorValue {
+    Name(String),
+    Index(u8),
+    Style(Style),
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+struct Style {
+    color: Option<String>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    bold: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    italic: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    underline: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    dim: bool,
+}
+
+#[allow(clippy::trivially_copy_pass_by_ref)]
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+impl ColorValue {
+    fn style(&self) -> Style {
+        match self {
+            ColorValue::Name(name) => Style {
+                color: Some(name.clone()),
+                ..Style::default()
+            },
+            ColorValue::Index(index) => Style {
+                color: Some(index.to_string()),
+                ..Style::default()
+            },
+            ColorValue::Style(style) => style.clone(),
+        }
+    }
+}
+
 #[derive(Debug)]
 struct Entry {
     path: PathBuf,
     metadata: Option<Metadata>,
+    link_target: Option<PathBuf>,
+    git_status: Option<git::Status>,
+    /// Whether the entry is hidden by the configuration, and only displayed
+    /// because of `-a`.
+    ignored: bool,
+    /// Whether the entry doesn't match the filters (e.g. `--type`). Such
+    /// directories are only kept so `-R` and `--tree` can list their contents.
+    filtered: bool,
+    /// The total size of a directory's contents, computed with `--du`.
+    dir_size: Option<u64>,
+    /// The digest of a file's contents, computed with `--hash`.
+    hash: Option<String>,
+    /// The content type of a file, detected with `--mime`.
+    mime: Option<&'static str>,
+    /// Details about media files (e.g. image dimensions), read with
+    /// `--media-info`.
+    media: Option<String>,
+    /// How many files an archive has, and their uncompressed size, read with
+    /// `--archive-info`.
+    archive: Option<(usize, u64)>,
+    /// The Linux capabilities of an executable (e.g.
+    /// `cap_net_bind_service+ep`), read with `--caps`.
+    capabilities: Option<String>,
 }
 
 #[derive(Error, Debug)]
@@ -56,402 +229,4493 @@ enum Error {
     #[error("couldn't find the specified path {0:?}")]
     PathNotFound(String),
 
+    #[error("invalid time format {0:?}")]
+    TimeFormat(String),
+
+    #[error("invalid config file {0:?}: {1}")]
+    Config(PathBuf, Box<toml::de::Error>),
+
+    #[error("couldn't find the config file {0:?}")]
+    ConfigNotFound(PathBuf),
+
+    #[error("invalid value for {0}: {1:?}")]
+    Env(String, String),
+
+    #[error("couldn't find the theme {0:?}")]
+    ThemeNotFound(String),
+
+    #[error("couldn't read the archive {0:?}: {1}")]
+    Archive(PathBuf, io::Error),
+
     #[error(transparent)]
     Io(#[from] std::io::Error),
 
     #[error(transparent)]
     Glob(#[from] glob::PatternError),
+
+    #[error(transparent)]
+    Serialize(#[from] toml::ser::Error),
 }
 
-/// A simple implementation of the `ls` command that uses
-/// [NerdFonts](https://www.nerdfonts.com/) and colored output by default.
-///
-#[derive(Parser, Debug)]
-#[command(version, about, long_about = None)]
-#[allow(clippy::struct_excessive_bools)]
-struct Cmd {
-    /// The entry that must be displayed.
-    /// Can also be a glob pattern.
-    path: Option<String>,
+/// The built-in color themes.
+const THEMES: [&str; 4] = ["dracula", "gruvbox", "nord", "solarized-light"];
 
-    /// Force output to be one entry per line.
-    #[arg(short = '1')]
-    single_column: bool,
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum ColorWhen {
+    /// Color the output when writing to a terminal.
+    Auto,
+    /// Always color the output.
+    Always,
+    /// Never color the output.
+    Never,
+}
 
-    /// Show all files and folders, disabling the `ignore` configuration.
-    #[arg(long, short = 'a')]
-    all: bool,
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum TimeStyle {
+    /// Show dates like `ls` does (e.g. `Oct 15 08:10`).
+    Default,
+    /// Show how long ago entries were modified (e.g. `3 hours ago`).
+    Relative,
 }
 
-fn main() {
-    match run() {
-        Ok(()) => (),
-        Err(error) => {
-            eprintln!("ERROR: {error}");
-            process::exit(1);
-        }
-    }
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum GroupDirs {
+    /// List directories before files.
+    First,
+    /// List directories after files.
+    Last,
+    /// Mix directories and files.
+    None,
 }
 
-fn run() -> Result<(), Error> {
-    let config = get_config()?;
-    let cmd = Cmd::parse();
-    let mut input = expand_path(
-        &cmd.path
-            .clone()
-            .unwrap_or(format!(".{MAIN_SEPARATOR}*").to_string()),
-    );
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum EntryType {
+    /// Regular files, including executables.
+    #[value(name = "f")]
+    File,
+    /// Directories.
+    #[value(name = "d")]
+    Directory,
+    /// Symlinks, including dead ones.
+    #[value(name = "l")]
+    Symlink,
+    /// Executable files.
+    #[value(name = "x")]
+    Executable,
+}
 
-    if let Ok(metadata) = fs::metadata(input.clone()) {
-        if metadata.is_dir() {
-            input = Path::new(&input).join("*").to_str().unwrap().to_string();
-        }
-    }
+/// A size threshold, as given to `--size`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct SizeFilter {
+    ordering: Ordering,
+    size: u64,
+}
 
-    let paths: Vec<PathBuf> = glob(input.clone().as_str())?
-        .filter_map(Result::ok)
-        .collect();
+fn parse_size_filter(value: &str) -> Result<SizeFilter, String> {
+    let (ordering, size) = if let Some(size) = value.strip_prefix('+') {
+        (Ordering::Greater, size)
+    } else if let Some(size) = value.strip_prefix('-') {
+        (Ordering::Less, size)
+    } else {
+        (Ordering::Equal, value)
+    };
 
-    let parent = Path::new(&input)
-        .parent()
-        .expect("Couldn't get the parent dir");
+    let size: bytesize::ByteSize = size.parse()?;
 
-    if let Ok(basedir) = fs::canonicalize(parent) {
-        show_entries(&cmd, &config, &paths, &basedir);
-        return Ok(());
+    Ok(SizeFilter {
+        ordering,
+        size: size.as_u64(),
+    })
+}
+
+/// Parse either a date (`YYYY-MM-DD`, in the local timezone) or a duration
+/// relative to now (e.g. `2d`), into the time it refers to.
+fn parse_time_threshold(value: &str) -> Result<SystemTime, String> {
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return date
+            .and_time(NaiveTime::MIN)
+            .and_local_timezone(Local)
+            .earliest()
+            .map(SystemTime::from)
+            .ok_or_else(|| format!("invalid local date {value:?}"));
     }
 
-    Err(Error::PathNotFound(input))
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (amount, unit) = value.split_at(split);
+    let amount: i64 = amount.parse().map_err(|_| {
+        format!("expected a date (YYYY-MM-DD) or a duration (e.g. 2d), got {value:?}")
+    })?;
+
+    let duration = match unit {
+        "s" => Duration::try_seconds(amount),
+        "m" => Duration::try_minutes(amount),
+        "h" => Duration::try_hours(amount),
+        "d" => Duration::try_days(amount),
+        "w" => Duration::try_weeks(amount),
+        "y" => amount.checked_mul(365).and_then(Duration::try_days),
+        _ => {
+            return Err(format!(
+                "unknown duration unit {unit:?} (use s, m, h, d, w, or y)"
+            ))
+        }
+    };
+
+    duration
+        .and_then(|duration| Local::now().checked_sub_signed(duration))
+        .map(SystemTime::from)
+        .ok_or_else(|| format!("the duration {value:?} is too long"))
 }
 
-fn get_color_from_string(color_name: &str) -> Color {
-    match color_name.to_lowercase().as_str() {
-        "red" => Color::Red,
-        "green" => Color::Green,
-        "yellow" => Color::Yellow,
-        "blue" => Color::Blue,
-        "magenta" => Color::Magenta,
-        "cyan" => Color::Cyan,
-        "white" => Color::White,
-        "grey" => Color::Grey,
-        "darkred" => Color::DarkRed,
-        "darkgreen" => Color::DarkGreen,
-        "darkyellow" => Color::DarkYellow,
-        "darkblue" => Color::DarkBlue,
-        "darkmagenta" => Color::DarkMagenta,
-        "darkcyan" => Color::DarkCyan,
-        "darkgrey" => Color::DarkGrey,
-        _ => Color::Black,
+/// The fields available to `--format`: the same as the JSON output, plus
+/// `bytes` for the exact size, as `size` is human-readable.
+const FORMAT_FIELDS: [&str; 13] = [
+    "name",
+    "path",
+    "type",
+    "target",
+    "size",
+    "bytes",
+    "mtime",
+    "btime",
+    "permissions",
+    "hash",
+    "mime",
+    "icon",
+    "color",
+];
+
+fn parse_format(value: &str) -> Result<template::Template, String> {
+    let template = template::Template::parse(value)?;
+
+    if let Some(field) = template
+        .fields()
+        .find(|field| !FORMAT_FIELDS.contains(field))
+    {
+        return Err(format!(
+            "unknown field {field:?} (available fields are {})",
+            FORMAT_FIELDS.join(", ")
+        ));
     }
+
+    Ok(template)
 }
 
-fn format_with_color(config: &Config, message: String, name: &str) -> String {
-    match supports_color::on(supports_color::Stream::Stdout) {
-        Some(_) => {
-            let default_color = "black".to_string();
-            let color_name = config.colors.get(name).unwrap_or(&default_color);
+#[cfg(unix)]
+fn parse_user(value: &str) -> Result<u32, String> {
+    value
+        .parse()
+        .ok()
+        .or_else(|| users::user_id(value))
+        .ok_or_else(|| format!("couldn't find the user {value:?}"))
+}
 
-            message.with(get_color_from_string(color_name)).to_string()
-        }
-        _ => message,
-    }
+#[cfg(unix)]
+fn parse_group(value: &str) -> Result<u32, String> {
+    value
+        .parse()
+        .ok()
+        .or_else(|| users::group_id(value))
+        .ok_or_else(|| format!("couldn't find the group {value:?}"))
 }
 
-fn resolve_icon(
-    icons: &HashMap<String, String>,
-    aliases: &HashMap<String, String>,
-    fallback: &str,
-    queries: Vec<String>,
-) -> String {
-    let mut icon = fallback.to_string();
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum SortBy {
+    /// Sort by name, alphabetically.
+    Name,
+    /// Sort by file size, largest first.
+    Size,
+    /// Sort by time (see `--time`), newest first.
+    Time,
+    /// Sort by extension, alphabetically.
+    Extension,
+    /// Sort by name, comparing numbers by their value (e.g. `file2` before
+    /// `file10`).
+    Version,
+}
 
-    for query in queries {
-        if let Some(value) = icons.get(&query) {
-            icon = value.to_string();
-            break;
+/// The timestamp displayed in the long listing format, and used to sort and
+/// filter entries by time.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum TimeField {
+    /// When the contents were last modified.
+    #[value(name = "mtime", alias = "modified")]
+    Modified,
+    /// When the contents were last read.
+    #[value(name = "atime", alias = "accessed")]
+    Accessed,
+    /// When the metadata (e.g. permissions or owner) was last changed. Not
+    /// available on Windows.
+    #[value(name = "ctime", alias = "changed")]
+    Changed,
+    /// When the entry was created, on platforms and filesystems that track it
+    /// (e.g. APFS, NTFS, and ext4). Otherwise, the modification time is used,
+    /// marked with `*`.
+    #[value(name = "btime", alias = "created", alias = "birth")]
+    Created,
+}
+
+impl TimeField {
+    fn title(self) -> &'static str {
+        match self {
+            TimeField::Modified => "Modified",
+            TimeField::Accessed => "Accessed",
+            TimeField::Changed => "Changed",
+            TimeField::Created => "Created",
         }
     }
+}
 
-    // Resolve aliases (only one level).
-    if let Some(found) = aliases.get(&icon) {
-        icon = found.clone();
-    }
+#[derive(Subcommand, Debug)]
+enum Commands {
+    /// Print the shell completion script for the given shell.
+    Completions {
+        #[arg(value_enum)]
+        shell: Shell,
+    },
 
-    icon
+    /// Print the man page, in roff format.
+    Man,
+
+    /// Find files with identical contents, in a directory and its
+    /// subdirectories.
+    Dupes {
+        /// The directory to search in.
+        path: Option<String>,
+
+        /// Ignore files smaller than the given size (e.g. `1M`). Empty files
+        /// are always ignored.
+        #[arg(long, value_name = "SIZE")]
+        min_size: Option<bytesize::ByteSize>,
+    },
+
+    /// Inspect the configuration.
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommands,
+    },
 }
 
-fn build_file_entry(config: &Config, metadata: &fs::Metadata, path: &Path, _pwd: &Path) -> String {
-    let dirname = path
-        .parent()
-        .expect("couldn't find parent dir")
-        .to_str()
-        .unwrap_or_default()
-        .to_string();
-    let basename = path
-        .file_name()
-        .unwrap_or_default()
-        .to_str()
-        .unwrap()
-        .to_string();
-    let ext = path
-        .extension()
-        .unwrap_or_default()
-        .to_str()
-        .unwrap()
-        .to_lowercase();
-    let ext = format!(".{ext}");
+#[derive(Subcommand, Debug)]
+enum ConfigCommands {
+    /// Print the effective configuration, after merging the defaults, the
+    /// theme, and your own configuration file.
+    Print,
+}
 
-    let icon = resolve_icon(
-        &config.files,
-        &config.aliases,
-        "\u{ea7b}",
-        vec![
-            format!("{dirname}/{basename}"),
-            basename.clone(),
-            ext,
-            "file".to_string(),
-        ],
-    );
+/// A simple implementation of the `ls` command that uses
+/// [NerdFonts](https://www.nerdfonts.com/) and colored output by default.
+///
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+#[allow(clippy::struct_excessive_bools)]
+struct Cmd {
+    #[command(subcommand)]
+    command: Option<Commands>,
 
-    let size = bytesize::ByteSize::b(get_file_size(metadata))
-        .to_string()
-        .replace(' ', "");
+    /// The entry that must be displayed.
+    /// Can also be a glob pattern.
+    path: Option<String>,
 
-    let color_type = if is_executable(path, metadata) {
-        "executable_file"
-    } else if basename.starts_with('.') {
-        "hidden"
-    } else {
-        "file"
-    };
+    /// Force output to be one entry per line.
+    #[arg(short = '1')]
+    single_column: bool,
 
-    let mut input = format_with_color(config, format!("  {icon} {basename}"), color_type);
+    /// List entries as a single comma-separated stream, wrapped at the
+    /// terminal's width.
+    #[arg(short = 'm', conflicts_with_all = ["long", "single_column", "tree"])]
+    commas: bool,
 
-    input = format!(
-        "{input} {}",
-        format_with_color(config, size.to_string(), "file_size")
-    );
+    /// Fill the grid across rows first, rather than down columns.
+    #[arg(short = 'x')]
+    across: bool,
 
-    input
-}
+    /// Use at most the given number of columns in the grid, even when more
+    /// would fit the terminal.
+    #[arg(long, value_name = "N")]
+    columns: Option<usize>,
 
-fn build_dir_entry(config: &Config, _metadata: &fs::Metadata, path: &Path) -> String {
-    let basename = path
-        .file_name()
-        .unwrap_or_default()
-        .to_str()
-        .unwrap()
-        .to_string();
-    let ext = path
-        .extension()
-        .unwrap_or_default()
-        .to_str()
-        .unwrap()
-        .to_lowercase();
-    let ext = format!(".{ext}");
+    /// The number of spaces between grid columns. Overrides the `column_gap`
+    /// configuration.
+    #[arg(long, value_name = "N")]
+    column_gap: Option<usize>,
 
-    let icon = resolve_icon(
-        &config.folders,
-        &config.aliases,
-        "\u{e5ff}",
-        vec![basename.clone(), ext, "folder".to_string()],
-    );
+    /// The character used to align columns in the long listing format (e.g.
+    /// `.` for dotted leaders). Overrides the `padding` configuration.
+    #[arg(long, value_name = "CHAR")]
+    padding: Option<char>,
 
-    let color_type = if basename.starts_with('.') {
-        "hidden_dir"
-    } else {
-        "dir"
-    };
+    /// Append a character to names telling their type, like `ls -F`: `*` for
+    /// executables, `@` for symlinks, `|` for FIFOs, and `=` for sockets.
+    #[arg(long, short = 'F')]
+    classify: bool,
 
-    let input = format!("  {icon} {basename}/");
+    /// Print names as they are. By default, names with spaces or special
+    /// characters are quoted like in a shell (e.g. `'my file'`), and control
+    /// characters are escaped (e.g. `$'new\nline'`).
+    #[arg(long, short = 'N')]
+    literal: bool,
 
-    format_with_color(config, input, color_type)
-}
+    /// Don't truncate entries that are wider than the terminal (e.g. to keep
+    /// full names with `-1`).
+    #[arg(long)]
+    no_truncate: bool,
 
-fn show_entries(cmd: &Cmd, config: &Config, paths: &[PathBuf], pwd: &PathBuf) {
-    let folders: Vec<String> = config
-        .ignore
-        .get("folders")
-        .expect("Couldn't get ignore.folders")
-        .iter()
-        .map(|s| s.to_lowercase())
-        .collect();
-    let files: Vec<String> = config
-        .ignore
-        .get("files")
-        .expect("Couldn't get ignore.files")
-        .iter()
-        .map(|s| s.to_lowercase())
-        .collect();
+    /// Lay out entries for the given number of columns, instead of the
+    /// terminal's width (or the `COLUMNS` variable).
+    #[arg(long, value_name = "N")]
+    width: Option<usize>,
 
-    let mut entries: Vec<Entry> = paths
-        .iter()
-        .map(|path| Entry {
-            path: path.clone(),
-            metadata: fs::metadata(path.clone())
-                .map_err(|_| Error::Metadata(path.clone()))
-                .ok(),
-        })
-        .filter(|entry| !entry.path.display().to_string().ends_with('.'))
-        .filter(|entry| cmd.all || ignore_entry(entry, &folders, &files))
-        .collect();
+    /// Show all files and folders, disabling the `ignore` configuration and
+    /// `--git-ignore`.
+    #[arg(long, short = 'a')]
+    all: bool,
 
-    entries.sort_by_key(|entry| {
-        entry
-            .path
-            .file_name()
-            .map(|name| name.to_os_string().to_ascii_lowercase())
-    });
+    /// Show hidden entries (like dotfiles), but still apply the `ignore`
+    /// configuration. This is the default, but it can be used to override a
+    /// previous `-a` (e.g. from a shell alias).
+    #[arg(long, short = 'A', overrides_with = "all")]
+    almost_all: bool,
 
-    let mut list: Vec<String> = vec![];
+    /// Use a long listing format, with permissions, owner, size and
+    /// modification time.
+    #[arg(short = 'l')]
+    long: bool,
 
-    for entry in entries {
-        let relative_path = pathdiff::diff_paths(&entry.path, pwd).unwrap_or(entry.path.clone());
+    /// How times are displayed in the long listing format.
+    #[arg(long, value_enum, default_value_t = TimeStyle::Default)]
+    time_style: TimeStyle,
 
-        let Some(metadata) = entry.metadata else {
-            let item = format_with_color(
-                config,
-                format!("  \u{f481} {}", relative_path.display()),
-                "dead_link",
-            );
+    /// A strftime-style format used to display times (e.g. `%Y-%m-%d %H:%M`).
+    /// Overrides the `time_format` configuration.
+    #[arg(long, value_name = "FORMAT")]
+    time_format: Option<String>,
 
-            list.push(item);
+    /// Show the number of entries inside each directory, next to its name (or
+    /// in the size column of the long listing format).
+    #[arg(long)]
+    count: bool,
 
-            continue;
-        };
+    /// Show the total size of each directory's contents, instead of leaving
+    /// directory sizes out. Subdirectories are walked to compute it, which can
+    /// be slow for large trees.
+    #[arg(long)]
+    du: bool,
 
-        let item = if metadata.is_dir() {
-            build_dir_entry(config, &metadata, &relative_path)
-        } else {
-            build_file_entry(config, &metadata, &relative_path, pwd)
-        };
+    /// Don't use (or update) the cache of directory sizes computed by `--du`.
+    #[arg(long, requires = "du")]
+    no_cache: bool,
 
-        list.push(item);
-    }
+    /// Show a bar after each size, proportional to the largest entry in the
+    /// listing.
+    #[arg(long)]
+    bars: bool,
 
-    if cmd.single_column {
-        for item in list {
-            println!("{item}");
-        }
-    } else {
-        display_in_columns(&list);
-    }
-}
+    /// Show a digest of each file's contents in the long listing format
+    /// (truncated) and in the JSON output. Files are hashed in parallel.
+    #[arg(long, value_enum, value_name = "ALGORITHM")]
+    hash: Option<hash::Algorithm>,
 
-fn get_config_file() -> Result<PathBuf, Error> {
-    let config_dir = if let Ok(config_home) = std::env::var("XDG_CONFIG_HOME") {
-        PathBuf::from(&config_home)
-    } else {
-        dirs::home_dir()
-            .ok_or(Error::HomeDirNotFound)?
-            .join(".config")
-    };
+    /// Show the content type of each file (e.g. `image/png`), detected from
+    /// its contents rather than its extension, in the long listing format and
+    /// in the JSON output.
+    #[arg(long)]
+    mime: bool,
 
-    Ok(config_dir.join("ll.toml"))
-}
+    /// Show details about media files next to their size in the long listing
+    /// format, like the dimensions of images (e.g. `1920x1080`) and the
+    /// duration of audio and video files (e.g. `3:25`).
+    #[arg(long)]
+    media_info: bool,
 
-fn get_config() -> Result<Config, Error> {
-    let toml_str = include_str!("config.toml");
-    let mut config: Config = toml::from_str(toml_str).expect("Failed to parse TOML file");
-    let config_file = get_config_file()?;
+    /// Show how many files each archive (zip and tar) has, and their
+    /// uncompressed size, next to its own size.
+    #[arg(long)]
+    archive_info: bool,
 
-    if config_file.exists() {
-        let toml_str = fs::read_to_string(&config_file)?;
-        let custom_config: OptionalConfig =
-            toml::from_str(&toml_str).expect("Failed to parse TOML file");
+    /// Only show the given number of entries, largest first. Use `-R` to
+    /// search for the largest files in subdirectories too.
+    #[arg(long, value_name = "N", conflicts_with = "tree")]
+    largest: Option<usize>,
 
-        config
-            .folders
-            .extend(custom_config.folders.unwrap_or_default());
+    /// Show how many files there are for each extension, and their total
+    /// size, instead of listing them. Use `-R` to include subdirectories.
+    #[arg(long, conflicts_with_all = ["tree", "largest"])]
+    stats: bool,
 
-        config.files.extend(custom_config.files.unwrap_or_default());
+    /// Show a header row with the column names in the long listing format.
+    #[arg(long, requires = "long")]
+    header: bool,
 
-        config
-            .colors
-            .extend(custom_config.colors.unwrap_or_default());
+    /// Show numeric user and group IDs in the long listing format, instead of
+    /// their names. Names aren't looked up at all, which is faster with slow
+    /// directory services (e.g. LDAP), and works in containers without
+    /// `/etc/passwd` entries.
+    #[arg(long = "numeric-uid-gid", short = 'n')]
+    numeric: bool,
 
-        config
-            .aliases
-            .extend(custom_config.aliases.unwrap_or_default());
+    /// Show permissions as an octal value (e.g. `0755`) in the long listing
+    /// format, alongside the permissions string.
+    #[arg(long)]
+    octal: bool,
 
-        let ignore = custom_config.ignore.unwrap_or_default();
+    /// Show the inode number (or file index on Windows) of each entry.
+    #[arg(long, short = 'i')]
+    inode: bool,
 
-        if let Some(files) = ignore.get("files") {
-            config.ignore.insert("files".to_string(), files.clone());
-        }
+    /// Sort entries by the given key.
+    #[arg(long, value_enum, default_value_t = SortBy::Name)]
+    sort: SortBy,
 
-        if let Some(folders) = ignore.get("folders") {
-            config.ignore.insert("folders".to_string(), folders.clone());
-        }
-    }
+    /// Sort by file size, largest first (same as `--sort=size`).
+    #[arg(short = 'S')]
+    sort_by_size: bool,
 
-    Ok(config)
-}
+    /// Sort by time (see `--time`), newest first (same as `--sort=time`).
+    #[arg(short = 't')]
+    sort_by_time: bool,
 
-fn display_in_columns(list: &[String]) {
-    let max_item_len = list
-        .iter()
-        .map(|i| visible_length(i))
-        .max()
-        .unwrap_or_default();
+    /// Sort by name, comparing numbers by their value (same as
+    /// `--sort=version`).
+    #[arg(short = 'v')]
+    sort_by_version: bool,
 
-    let term_width: usize = if let Ok((width, _)) = terminal::size() {
-        width.into()
-    } else {
-        1
-    };
+    /// The timestamp displayed in the long listing format, and used by
+    /// `--sort=time`, `--newer-than`, and `--older-than`.
+    #[arg(long, value_enum, value_name = "FIELD", default_value_t = TimeField::Modified)]
+    time: TimeField,
 
-    let list_len = list.len();
-    let col_gap = 2;
-    let col_width = max_item_len + col_gap;
-    let mut cols = max(1, term_width / col_width);
+    /// Reverse the sorting order.
+    #[arg(long, short = 'r')]
+    reverse: bool,
 
-    // similar result to using .ceil() on a floating-point division
-    let mut rows = max(1, (list_len + cols - 1) / cols);
+    /// Group directories before or after files.
+    #[arg(long, value_enum, default_value_t = GroupDirs::None)]
+    group_dirs: GroupDirs,
 
-    if rows == 1 {
-        cols = 1;
-        rows = list_len;
-    }
+    /// Recursively list directories as a tree.
+    #[arg(long, conflicts_with = "long")]
+    tree: bool,
 
-    for row in 0..rows {
-        for col in 0..cols {
-            let index = col * rows + row;
+    /// Recursively list subdirectories, each one in its own section.
+    #[arg(long, short = 'R', conflicts_with = "tree")]
+    recursive: bool,
 
-            if index < list_len {
-                let value = &list[index];
-                let value_len = visible_length(value);
-                let padding = " ".repeat(col_width - value_len);
+    /// Limit how deep `--tree` and `-R` descend into directories.
+    #[arg(long, value_name = "N")]
+    depth: Option<usize>,
 
-                print!("{value}{padding}");
-            }
-        }
+    /// Choose which columns are displayed by `-l`, and their order (e.g.
+    /// `name,size,mtime,perms`). Columns that need extra work, like `hash`,
+    /// are computed when selected.
+    #[arg(
+        long,
+        value_enum,
+        value_name = "FIELDS",
+        value_delimiter = ',',
+        requires = "long"
+    )]
+    fields: Vec<Column>,
 
-        println!();
-    }
-}
+    /// Show the git status of each entry, when listing a git repository.
+    #[arg(long)]
+    git: bool,
 
-fn visible_length(input: &str) -> usize {
-    let ansi_escape = Regex::new(r"\x1b\[[0-9;]*m").unwrap();
-    let stripped = ansi_escape.replace_all(input, "");
+    /// Hide entries whose names match the given glob pattern (e.g. `*.log`).
+    /// Can be used multiple times.
+    #[arg(long, value_name = "GLOB")]
+    ignore_glob: Vec<glob::Pattern>,
 
-    stripped.chars().count()
-}
+    /// Only show entries whose names match the given glob pattern (e.g.
+    /// `*.rs`). Can be used multiple times. Directories are always shown with
+    /// `--tree` and `-R`, so matching entries inside them can be listed.
+    #[arg(long, value_name = "GLOB")]
+    only: Vec<glob::Pattern>,
 
-#[cfg(unix)]
-fn is_executable(_path: &Path, metadata: &Metadata) -> bool {
-    metadata.permissions().mode() & 0o111 != 0
-}
+    /// Only show entries whose names match the given regular expression (e.g.
+    /// `^test_.*_v\d+`).
+    #[arg(long, value_name = "REGEX")]
+    r#match: Option<Regex>,
 
-#[cfg(windows)]
-fn is_executable(path: &Path, _metadata: &Metadata) -> bool {
-    if let Some(ext) = path.extension() {
-        matches!(ext.to_str(), Some("exe" | "bat" | "cmd"))
+    /// Only show entries with the given extensions, ignoring case (e.g.
+    /// `--ext rs,toml`).
+    #[arg(long, value_name = "EXT", value_delimiter = ',')]
+    ext: Vec<String>,
+
+    /// Only show files larger (`+10M`) or smaller (`-4K`) than the given size,
+    /// or with exactly the given size (`512B`).
+    #[arg(long, value_name = "SIZE", value_parser = parse_size_filter, allow_hyphen_values = true)]
+    size: Option<SizeFilter>,
+
+    /// Only show entries modified after the given date (e.g. `2024-01-01`), or
+    /// within the given duration (e.g. `30m`, `2d`, or `1w`). Uses the
+    /// timestamp selected with `--time`.
+    #[arg(long, value_name = "WHEN", value_parser = parse_time_threshold)]
+    newer_than: Option<SystemTime>,
+
+    /// Only show entries modified before the given date (e.g. `2024-01-01`),
+    /// or longer ago than the given duration (e.g. `30m`, `2d`, or `1w`). Uses
+    /// the timestamp selected with `--time`.
+    #[arg(long, value_name = "WHEN", value_parser = parse_time_threshold)]
+    older_than: Option<SystemTime>,
+
+    /// Only show entries owned by the given user (name or uid).
+    #[cfg(unix)]
+    #[arg(long, value_name = "USER", value_parser = parse_user)]
+    owner: Option<u32>,
+
+    /// Only show entries owned by the given group (name or gid).
+    #[cfg(unix)]
+    #[arg(long, value_name = "GROUP", value_parser = parse_group)]
+    group: Option<u32>,
+
+    /// Only show symlinks whose targets don't exist. Use `-R` to find them in
+    /// subdirectories too.
+    #[arg(long)]
+    broken_links: bool,
+
+    /// Only show entries of the given types (e.g. `--type f,l`).
+    #[arg(long, value_enum, value_name = "TYPE", value_delimiter = ',')]
+    r#type: Vec<EntryType>,
+
+    /// Hide entries ignored by git (through `.gitignore` files,
+    /// `.git/info/exclude`, or the global gitignore).
+    #[arg(long)]
+    git_ignore: bool,
+
+    /// Print the paths of the entries separated by NUL bytes, without icons
+    /// or colors, so they can be piped into `xargs -0`. With `-R`, files in
+    /// subdirectories are printed too.
+    #[arg(long, short = '0', conflicts_with_all = ["long", "tree", "json", "stats"])]
+    print0: bool,
+
+    /// Output entries as a JSON array.
+    #[arg(long, conflicts_with_all = ["long", "tree", "recursive"])]
+    json: bool,
+
+    /// Output entries as JSON Lines, one object per line. Entries are written
+    /// as each directory is read, so `-R` output can be consumed right away.
+    #[arg(long, conflicts_with_all = ["long", "tree", "json", "print0"])]
+    jsonl: bool,
+
+    /// Display each entry using a template, like `{icon} {name:<30} {size:>8}`.
+    /// Fields are the same as `--json`, plus `bytes` for the exact size. They
+    /// can be padded to a width and aligned to the left (`<`), right (`>`), or
+    /// center (`^`).
+    #[arg(
+        long,
+        value_name = "TEMPLATE",
+        value_parser = parse_format,
+        conflicts_with_all = ["long", "tree", "recursive", "json", "jsonl", "print0"]
+    )]
+    format: Option<template::Template>,
+
+    /// Output entries as an HTML page, with colors as CSS classes. Useful for
+    /// generating directory index pages.
+    #[arg(long, conflicts_with_all = ["long", "tree", "recursive", "json", "jsonl", "print0"])]
+    html: bool,
+
+    /// Output entries as tab-separated values (name, type, size, modification
+    /// time, creation time, and permissions), without colors.
+    #[arg(long, conflicts_with_all = ["long", "tree", "recursive", "json", "jsonl", "print0", "html"])]
+    tsv: bool,
+
+    /// Output entries as CSV, with a header row and the same fields as
+    /// `--json`.
+    #[arg(long, conflicts_with_all = ["long", "tree", "recursive", "json", "jsonl", "print0", "html", "tsv"])]
+    csv: bool,
+
+    /// Don't display icons, for terminals without a NerdFonts-patched font.
+    #[arg(long)]
+    no_icons: bool,
+
+    /// Make entry names clickable on terminals that support OSC 8 hyperlinks.
+    #[arg(long)]
+    hyperlink: bool,
+
+    /// Show thumbnails instead of icons for image files, on terminals that
+    /// support the kitty or iTerm2 graphics protocols.
+    #[arg(long)]
+    thumbnails: bool,
+
+    /// When to color the output. `auto` also honors the `NO_COLOR` and
+    /// `CLICOLOR_FORCE` environment variables.
+    #[arg(long, value_enum, value_name = "WHEN", default_value_t = ColorWhen::Auto)]
+    color: ColorWhen,
+
+    /// Load the configuration from the given file, instead of
+    /// `$XDG_CONFIG_HOME/ll.toml`. A project's `.ll.toml` is still applied on
+    /// top of it.
+    #[arg(long, value_name = "PATH", global = true)]
+    config: Option<PathBuf>,
+
+    /// The color theme to use. Overrides the `theme` configuration, but colors
+    /// defined in the configuration file still take precedence.
+    #[arg(long, value_parser = PossibleValuesParser::new(THEMES))]
+    theme: Option<String>,
+
+    /// Use the colors defined by the `LS_COLORS` environment variable, when
+    /// set.
+    #[arg(long)]
+    ls_colors: bool,
+
+    /// List directories themselves, not their contents.
+    #[arg(long, short = 'd', conflicts_with_all = ["tree", "recursive"])]
+    directory: bool,
+
+    /// Display how many directories and files were listed, and their total
+    /// size, after the listing.
+    #[arg(long)]
+    summary: bool,
+
+    /// Show the space allocated on disk, which is smaller than the size for
+    /// sparse and compressed files. In the long listing format it's shown in
+    /// its own column, next to the size; otherwise it replaces the size.
+    #[arg(long)]
+    allocated: bool,
+
+    /// Show the space allocated on disk next to the size of each file, like
+    /// `10GB (2.1GB on disk)`.
+    #[arg(long, conflicts_with = "allocated")]
+    on_disk: bool,
+
+    /// Show the flags that restrict what can be done with each entry in the
+    /// long listing format, like the `chattr` attributes on Linux (e.g. `i`
+    /// for immutable, `a` for append-only, and `C` for no copy-on-write), or
+    /// the `chflags` flags on macOS and BSD (e.g. `uchg`, `schg`, and
+    /// `hidden`).
+    #[arg(long, requires = "long")]
+    flags: bool,
+
+    /// Show the Linux capabilities of executables that have them (e.g.
+    /// `cap_net_bind_service+ep`), next to their names.
+    #[arg(long)]
+    caps: bool,
+
+    /// Show the SELinux security context of each entry in the long listing
+    /// format, like `ls -Z`. Entries without one display `?`.
+    #[arg(long, short = 'Z', requires = "long")]
+    context: bool,
+
+    /// List the extended attributes of each entry (on Linux and macOS), and
+    /// the size of their values, under it in the long listing format. Entries
+    /// with extended attributes are always marked with `@` after their
+    /// permissions.
+    #[arg(short = '@', requires = "long")]
+    xattrs: bool,
+
+    /// Display sizes as exact byte counts (e.g. `14256`), rather than
+    /// humanized (e.g. `14.3KB`).
+    #[arg(long)]
+    bytes: bool,
+
+    /// Display sizes in powers of 1000 (e.g. `KB` and `MB`). Overrides the
+    /// `binary_sizes` configuration.
+    #[arg(long, conflicts_with = "binary")]
+    si: bool,
+
+    /// Display sizes in powers of 1024 (e.g. `KiB` and `MiB`). Overrides the
+    /// `binary_sizes` configuration.
+    #[arg(long)]
+    binary: bool,
+
+    /// Don't display file sizes next to names. Overrides the `sizes`
+    /// configuration.
+    #[arg(long)]
+    no_size: bool,
+
+    /// Display the output through `$PAGER` (or `less -R`) when it doesn't fit
+    /// the terminal.
+    #[arg(long)]
+    pager: bool,
+
+    /// Keep refreshing the listing, until interrupted.
+    #[arg(long, short = 'w', conflicts_with = "json")]
+    watch: bool,
+
+    /// How often `--watch` refreshes the listing. Entries created, removed, or
+    /// renamed in the listed directory refresh it right away.
+    #[arg(long, value_name = "SECONDS", default_value_t = 2, requires = "watch")]
+    interval: u64,
+}
+
+impl Cmd {
+    fn sort_by(&self) -> SortBy {
+        if self.sort_by_size {
+            SortBy::Size
+        } else if self.sort_by_time {
+            SortBy::Time
+        } else if self.sort_by_version {
+            SortBy::Version
+        } else {
+            self.sort
+        }
+    }
+}
+
+fn main() {
+    match run() {
+        Ok(()) => (),
+        // The output was closed early (e.g. when piping to `head`).
+        Err(Error::Io(error)) if error.kind() == io::ErrorKind::BrokenPipe => (),
+        Err(error) => {
+            eprintln!("ERROR: {error}");
+            process::exit(1);
+        }
+    }
+}
+
+fn run() -> Result<(), Error> {
+    let mut cmd = Cmd::parse();
+
+    // Columns selected with `--fields` are computed as if their flags were
+    // used.
+    for column in &cmd.fields {
+        match column {
+            Column::Media => cmd.media_info = true,
+            Column::Mime => cmd.mime = true,
+            Column::Hash if cmd.hash.is_none() => cmd.hash = Some(hash::Algorithm::Sha256),
+            _ => {}
+        }
+    }
+
+    match cmd.command {
+        Some(Commands::Completions { shell }) => {
+            clap_complete::generate(shell, &mut Cmd::command(), "ll", &mut io::stdout());
+            return Ok(());
+        }
+        Some(Commands::Man) => {
+            clap_mangen::Man::new(Cmd::command()).render(&mut io::stdout())?;
+            return Ok(());
+        }
+        Some(Commands::Config {
+            command: ConfigCommands::Print,
+        }) => {
+            print!("{}", format_config(&get_config(&cmd)?)?);
+            return Ok(());
+        }
+        Some(Commands::Dupes { ref path, min_size }) => {
+            let mut config = get_config(&cmd)?;
+            config.full_paths = true;
+
+            let dir = expand_path(path.as_deref().unwrap_or("."));
+            let min_size = min_size.map_or(1, |size| size.as_u64().max(1));
+
+            return show_dupes(
+                &mut io::stdout().lock(),
+                &cmd,
+                &config,
+                Path::new(&dir),
+                min_size,
+            );
+        }
+        None => {}
+    }
+
+    let mut config = get_config(&cmd)?;
+
+    if cmd.no_icons {
+        config.icons = false;
+    }
+
+    if cmd.hyperlink {
+        config.hyperlink = true;
+    }
+
+    if cmd.thumbnails {
+        config.thumbnails = thumbnails::detect();
+    }
+
+    config.count = cmd.count;
+
+    if let Some(column_gap) = cmd.column_gap {
+        config.column_gap = column_gap;
+    }
+
+    if let Some(padding) = cmd.padding {
+        config.padding = padding;
+    }
+    config.classify = cmd.classify;
+    config.literal = cmd.literal;
+
+    // The largest files are searched in subdirectories with `-R`, so they need
+    // to show where they are.
+    config.full_paths = cmd.largest.is_some() && cmd.recursive;
+
+    if cmd.pager {
+        config.pager = true;
+    }
+
+    if cmd.summary {
+        config.summary = true;
+    }
+
+    if cmd.no_size {
+        config.sizes = false;
+    }
+
+    if cmd.si {
+        config.binary_sizes = false;
+    }
+
+    if cmd.binary {
+        config.binary_sizes = true;
+    }
+
+    config.bytes = cmd.bytes;
+    config.allocated = cmd.allocated;
+    config.on_disk = cmd.on_disk;
+
+    if cmd.git_ignore {
+        config.git_ignore = true;
+    }
+
+    config.colorize = should_colorize(cmd.color);
+
+    if let Some(time_format) = &cmd.time_format {
+        config.time_format = Some(time_format.clone());
+    }
+
+    if let Some(time_format) = &config.time_format {
+        if StrftimeItems::new(time_format).any(|item| item == Item::Error) {
+            return Err(Error::TimeFormat(time_format.clone()));
+        }
+    }
+
+    if !cmd.watch {
+        return list(&cmd, &config);
+    }
+
+    let (_, basedir) = resolve_paths(&cmd)?;
+    let watcher = watch::Watcher::new(&basedir, cmd.tree || cmd.recursive);
+    let interval = time::Duration::from_secs(cmd.interval);
+
+    loop {
+        let mut stdout = std::io::stdout();
+        execute!(
+            stdout,
+            terminal::Clear(ClearType::All),
+            cursor::MoveTo(0, 0)
+        )?;
+        list(&cmd, &config)?;
+        stdout.flush()?;
+
+        match &watcher {
+            Some(watcher) => watcher.wait(interval),
+            None => thread::sleep(interval),
+        }
+    }
+}
+
+fn list(cmd: &Cmd, config: &Config) -> Result<(), Error> {
+    // The output must be buffered to know whether it fits the terminal.
+    if config.pager && !cmd.watch && !cmd.print0 {
+        let mut output = vec![];
+        show_path(&mut output, cmd, config)?;
+
+        return Ok(pager::show(&output, visible_length)?);
+    }
+
+    show_path(&mut io::stdout().lock(), cmd, config)
+}
+
+fn show_path(out: &mut impl Write, cmd: &Cmd, config: &Config) -> Result<(), Error> {
+    // Archives are listed as if they were directories, unless `-d` is used.
+    let archive = cmd
+        .path
+        .as_deref()
+        .map(|path| PathBuf::from(expand_path(path)))
+        .filter(|path| {
+            !cmd.directory
+                && !cmd.print0
+                && !cmd.html
+                && path.is_file()
+                && archive::is_archive(path)
+        });
+
+    if let Some(archive) = archive {
+        return show_archive(out, cmd, config, &archive);
+    }
+
+    let (paths, basedir) = resolve_paths(cmd)?;
+
+    let repo = if cmd.git {
+        git::Repository::discover(&basedir)
+    } else {
+        None
+    };
+
+    show_entries(out, cmd, config, repo.as_ref(), &paths, &basedir)?;
+
+    Ok(())
+}
+
+/// Expand the path given on the command line into the entries that must be
+/// listed, along with the directory they're relative to.
+fn resolve_paths(cmd: &Cmd) -> Result<(Vec<PathBuf>, PathBuf), Error> {
+    let input = resolve_input(cmd);
+
+    // Directories are read directly, as glob patterns can't match names that
+    // are not valid UTF-8.
+    let dir = input
+        .strip_suffix(&format!("{MAIN_SEPARATOR}*"))
+        .filter(|dir| Path::new(dir).is_dir());
+
+    let paths: Vec<PathBuf> = match dir {
+        Some(dir) => fs::read_dir(dir)?
+            .filter_map(Result::ok)
+            .map(|child| match dir {
+                "." => PathBuf::from(child.file_name()),
+                _ => child.path(),
+            })
+            .collect(),
+        None => glob(input.clone().as_str())?
+            .filter_map(Result::ok)
+            .collect(),
+    };
+
+    match resolve_basedir(&input) {
+        Some(basedir) => Ok((paths, basedir)),
+        None => Err(Error::PathNotFound(input)),
+    }
+}
+
+/// Expand the path given on the command line into a glob pattern. Directories
+/// match their contents, unless `-d` is used.
+fn resolve_input(cmd: &Cmd) -> String {
+    let default_path = if cmd.directory {
+        ".".to_string()
+    } else {
+        format!(".{MAIN_SEPARATOR}*")
+    };
+    let mut input = expand_path(&cmd.path.clone().unwrap_or(default_path));
+
+    if let Ok(metadata) = fs::metadata(input.clone()) {
+        if metadata.is_dir() && !cmd.directory {
+            input = Path::new(&input).join("*").to_string_lossy().to_string();
+        }
+    }
+
+    input
+}
+
+/// Return the directory that the entries matched by the input are relative
+/// to.
+fn resolve_basedir(input: &str) -> Option<PathBuf> {
+    let parent = Path::new(input)
+        .parent()
+        .expect("Couldn't get the parent dir");
+
+    // Paths like `Cargo.toml` have an empty parent, which can't be
+    // canonicalized.
+    let parent = if parent.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        parent
+    };
+
+    fs::canonicalize(parent).ok()
+}
+
+fn get_color_from_string(color_name: &str) -> Color {
+    parse_color(color_name).unwrap_or(Color::Black)
+}
+
+fn parse_color(color_name: &str) -> Option<Color> {
+    let color_name = color_name.trim().to_lowercase();
+
+    if let Some(color) = parse_rgb_color(&color_name).or_else(|| parse_ansi_color(&color_name)) {
+        return Some(color);
+    }
+
+    let color = match color_name.as_str() {
+        "black" => Color::Black,
+        "red" => Color::Red,
+        "green" => Color::Green,
+        "yellow" => Color::Yellow,
+        "blue" => Color::Blue,
+        "magenta" => Color::Magenta,
+        "cyan" => Color::Cyan,
+        "white" => Color::White,
+        "grey" => Color::Grey,
+        "darkred" => Color::DarkRed,
+        "darkgreen" => Color::DarkGreen,
+        "darkyellow" => Color::DarkYellow,
+        "darkblue" => Color::DarkBlue,
+        "darkmagenta" => Color::DarkMagenta,
+        "darkcyan" => Color::DarkCyan,
+        "darkgrey" => Color::DarkGrey,
+        _ => return None,
+    };
+
+    Some(color)
+}
+
+/// Parse truecolor values, either as `#rrggbb` or `rgb(r, g, b)`.
+fn parse_rgb_color(color_name: &str) -> Option<Color> {
+    if let Some(hex) = color_name.strip_prefix('#') {
+        if hex.len() != 6 || !hex.is_ascii() {
+            return None;
+        }
+
+        let channel = |index: usize| u8::from_str_radix(&hex[index..index + 2], 16).ok();
+
+        return Some(Color::Rgb {
+            r: channel(0)?,
+            g: channel(2)?,
+            b: channel(4)?,
+        });
+    }
+
+    let channels: Vec<u8> = color_name
+        .strip_prefix("rgb(")?
+        .strip_suffix(')')?
+        .split(',')
+        .map(|channel| channel.trim().parse().ok())
+        .collect::<Option<_>>()?;
+
+    let [r, g, b] = channels[..] else {
+        return None;
+    };
+
+    Some(Color::Rgb { r, g, b })
+}
+
+/// Parse 256-color values, either as `ansi(208)` or just `208`.
+fn parse_ansi_color(color_name: &str) -> Option<Color> {
+    let value = color_name
+        .strip_prefix("ansi(")
+        .and_then(|value| value.strip_suffix(')'))
+        .unwrap_or(color_name);
+
+    value.trim().parse().ok().map(Color::AnsiValue)
+}
+
+fn should_colorize(when: ColorWhen) -> bool {
+    let no_color = std::env::var("NO_COLOR").is_ok_and(|value| !value.is_empty());
+    let force_color =
+        std::env::var("CLICOLOR_FORCE").is_ok_and(|value| !value.is_empty() && value != "0");
+
+    match when {
+        ColorWhen::Always => true,
+        ColorWhen::Never => false,
+        ColorWhen::Auto if no_color => false,
+        ColorWhen::Auto if force_color => true,
+        ColorWhen::Auto => supports_color::on(supports_color::Stream::Stdout).is_some(),
+    }
+}
+
+fn format_with_color(config: &Config, message: String, name: &str) -> String {
+    if !config.colorize {
+        return message;
+    }
+
+    let style = config.colors.get(name).map_or(
+        Style {
+            color: Some("black".to_string()),
+            ..Style::default()
+        },
+        ColorValue::style,
+    );
+
+    let mut output = message.stylize();
+
+    if let Some(color) = &style.color {
+        output = output.with(get_color_from_string(color));
+    }
+
+    if style.bold {
+        output = output.bold();
+    }
+
+    if style.italic {
+        output = output.italic();
+    }
+
+    if style.underline {
+        output = output.underlined();
+    }
+
+    if style.dim {
+        output = output.dim();
+    }
+
+    output.to_string()
+}
+
+fn with_icon(config: &Config, icon: &str, label: &str) -> String {
+    if config.icons {
+        format!("{icon} {label}")
+    } else {
+        label.to_string()
+    }
+}
+
+fn resolve_icon(
+    icons: &HashMap<String, String>,
+    aliases: &HashMap<String, String>,
+    fallback: &str,
+    queries: Vec<String>,
+) -> String {
+    let mut icon = fallback.to_string();
+
+    for query in queries {
+        if let Some(value) = icons.get(&query) {
+            icon = value.to_string();
+            break;
+        }
+    }
+
+    // Resolve aliases (only one level).
+    if let Some(found) = aliases.get(&icon) {
+        icon = found.clone();
+    }
+
+    icon
+}
+
+// `path` is the path to be displayed, while `source` is the actual location
+// of the file, used to read its contents.
+fn build_file_name(config: &Config, metadata: &fs::Metadata, path: &Path, source: &Path) -> String {
+    let basename = get_display_name(config, path);
+
+    let icon = config
+        .thumbnails
+        .and_then(|protocol| thumbnails::render(protocol, source, get_file_size(metadata)))
+        .unwrap_or_else(|| get_file_icon(config, Some(metadata), path, source));
+    let color_type = get_file_color_type(config, metadata, path, source);
+    let label = format_with_color(config, with_icon(config, &icon, &basename), color_type);
+
+    match get_classifier(path, metadata).filter(|_| config.classify) {
+        Some(classifier) => format!("{label}{classifier}"),
+        None => label,
+    }
+}
+
+fn get_display_name(config: &Config, path: &Path) -> String {
+    if config.full_paths {
+        return format_name(config, path);
+    }
+
+    // Paths like `.` and `..` (e.g. when using `-d`) have no file name.
+    format_name(
+        config,
+        Path::new(path.file_name().unwrap_or(path.as_os_str())),
+    )
+}
+
+fn format_name(config: &Config, path: &Path) -> String {
+    let name = path.to_string_lossy();
+
+    if config.literal {
+        name.to_string()
+    } else {
+        quote_name(&name)
+    }
+}
+
+/// Quote names that would break the layout, or that couldn't be pasted into a
+/// shell as they are, like `ls` does: `'my file'`, `'it'\''s'`, and
+/// `$'new\nline'` for names with control characters.
+fn quote_name(name: &str) -> String {
+    let is_safe = |char: char| {
+        char.is_ascii_alphanumeric()
+            || "%+,-./:=@_".contains(char)
+            || (!char.is_ascii() && !char.is_control() && !char.is_whitespace())
+    };
+
+    if !name.is_empty() && name.chars().all(is_safe) {
+        return name.to_string();
+    }
+
+    if !name.chars().any(char::is_control) {
+        return format!("'{}'", name.replace('\'', "'\\''"));
+    }
+
+    let escaped: String = name
+        .chars()
+        .map(|char| match char {
+            '\n' => "\\n".to_string(),
+            '\t' => "\\t".to_string(),
+            '\r' => "\\r".to_string(),
+            '\x1b' => "\\e".to_string(),
+            '\'' => "\\'".to_string(),
+            '\\' => "\\\\".to_string(),
+            char if char.is_control() => format!("\\x{:02x}", u32::from(char)),
+            char => char.to_string(),
+        })
+        .collect();
+
+    format!("$'{escaped}'")
+}
+
+fn get_file_icon(
+    config: &Config,
+    metadata: Option<&Metadata>,
+    path: &Path,
+    source: &Path,
+) -> String {
+    let dirname = path
+        .parent()
+        .expect("couldn't find parent dir")
+        .to_string_lossy()
+        .to_string();
+    let basename = path
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+    let ext = path
+        .extension()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_lowercase();
+    let ext = format!(".{ext}");
+    let mut queries = vec![format!("{dirname}/{basename}"), basename];
+    let has_icon = queries.iter().any(|query| config.files.contains_key(query));
+
+    // Sparse files have their own icon, unless their name already has one.
+    if !has_icon && metadata.is_some_and(is_sparse) {
+        return resolve_icon(
+            &config.kinds,
+            &config.aliases,
+            "\u{f0a0}",
+            vec!["sparse_file".to_string()],
+        );
+    }
+
+    // Regular files without an extension (e.g. scripts) are identified by
+    // their contents instead, unless their name already has an icon.
+    if ext == "." {
+        let is_file = metadata.is_some_and(Metadata::is_file);
+
+        if is_file && !has_icon {
+            let content_type = mime::detect(source).unwrap_or_default();
+
+            if config.content_types.contains_key(content_type) {
+                return resolve_icon(
+                    &config.content_types,
+                    &config.aliases,
+                    "\u{ea7b}",
+                    vec![content_type.to_string()],
+                );
+            }
+        }
+    } else {
+        queries.push(ext);
+    }
+
+    queries.push("file".to_string());
+
+    resolve_icon(&config.files, &config.aliases, "\u{ea7b}", queries)
+}
+
+fn get_file_color_type<'a>(
+    config: &'a Config,
+    metadata: &Metadata,
+    path: &Path,
+    source: &Path,
+) -> &'a str {
+    if is_setuid(metadata) {
+        return "warning";
+    }
+
+    if is_executable(path, metadata) {
+        return "executable_file";
+    }
+
+    if let Some(color_type) = match_color_rule(config, path) {
+        return color_type;
+    }
+
+    if is_hidden(path) {
+        "hidden"
+    } else if is_sparse(metadata) {
+        "sparse_file"
+    } else if config.detect_binary_files && mime::is_binary(source) {
+        "binary_file"
+    } else {
+        "file"
+    }
+}
+
+/// Find the first color rule whose glob pattern matches the entry's name.
+fn match_color_rule<'a>(config: &'a Config, path: &Path) -> Option<&'a str> {
+    let basename = path.file_name().unwrap_or_default().to_string_lossy();
+    let options = glob::MatchOptions {
+        case_sensitive: false,
+        ..glob::MatchOptions::new()
+    };
+
+    config
+        .color_rules
+        .iter()
+        .find(|(pattern, _)| pattern.matches_with(&basename, options))
+        .map(|(_, color_type)| color_type.as_str())
+}
+
+fn format_file_size(config: &Config, metadata: &Metadata) -> String {
+    format_size(config, get_file_size(metadata))
+}
+
+fn format_size(config: &Config, size: u64) -> String {
+    if config.bytes {
+        return format_count(config, size);
+    }
+
+    // Binary kilobytes are formatted as `kiB`, rather than the usual `KiB`.
+    bytesize::ByteSize::b(size)
+        .to_string_as(config.binary_sizes)
+        .replace(' ', "")
+        .replace("kiB", "KiB")
+}
+
+fn build_dir_entry(config: &Config, _metadata: &fs::Metadata, path: &Path) -> String {
+    let basename = get_display_name(config, path);
+
+    let icon = get_dir_icon(config, path);
+    let input = with_icon(config, &icon, &format!("{basename}/"));
+
+    format_with_color(config, input, get_dir_color_type(config, path))
+}
+
+fn get_dir_icon(config: &Config, path: &Path) -> String {
+    let basename = path
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+    let ext = path
+        .extension()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_lowercase();
+    let ext = format!(".{ext}");
+
+    resolve_icon(
+        &config.folders,
+        &config.aliases,
+        "\u{e5ff}",
+        vec![basename, ext, "folder".to_string()],
+    )
+}
+
+fn get_dir_color_type<'a>(config: &'a Config, path: &Path) -> &'a str {
+    if let Some(color_type) = match_color_rule(config, path) {
+        return color_type;
+    }
+
+    if is_hidden(path) {
+        "hidden_dir"
+    } else {
+        "dir"
+    }
+}
+
+/// Whether an entry is hidden, either because it's a dotfile or because it has
+/// the `hidden` flag (on macOS and BSD).
+fn is_hidden(path: &Path) -> bool {
+    path.file_name()
+        .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+        || flags::is_hidden(path)
+}
+
+fn build_link_entry(config: &Config, metadata: &Metadata, path: &Path, target: &str) -> String {
+    let mut basename = get_display_name(config, path);
+
+    if config.classify {
+        basename.push('@');
+    }
+
+    let icon = get_link_icon(config, metadata);
+
+    format_with_color(
+        config,
+        with_icon(config, &icon, &format!("{basename}{target}")),
+        "link",
+    )
+}
+
+fn get_link_icon(config: &Config, metadata: &Metadata) -> String {
+    if metadata.is_dir() {
+        resolve_icon(
+            &config.kinds,
+            &config.aliases,
+            "\u{f482}",
+            vec!["folder_symlink".to_string()],
+        )
+    } else {
+        resolve_icon(
+            &config.kinds,
+            &config.aliases,
+            "\u{f481}",
+            vec!["file_symlink".to_string()],
+        )
+    }
+}
+
+/// The details of an entry, as exposed by the structured output formats.
+#[derive(Serialize, Debug)]
+struct EntryInfo {
+    name: String,
+    path: String,
+    #[serde(rename = "type")]
+    kind: &'static str,
+    target: Option<String>,
+    size: Option<u64>,
+    mtime: Option<String>,
+    /// The creation time, when the platform and filesystem track it.
+    btime: Option<String>,
+    permissions: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hash: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mime: Option<&'static str>,
+    icon: String,
+    color: String,
+}
+
+fn build_entry_info(config: &Config, entry: &Entry) -> EntryInfo {
+    let name = entry
+        .path
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+    let path = entry.path.display().to_string();
+    let target = entry
+        .link_target
+        .as_ref()
+        .map(|target| target.display().to_string());
+
+    let Some(metadata) = &entry.metadata else {
+        return EntryInfo {
+            name,
+            path,
+            kind: "dead_link",
+            target,
+            size: None,
+            mtime: None,
+            btime: None,
+            permissions: None,
+            hash: None,
+            mime: None,
+            icon: "\u{f481}".to_string(),
+            color: "dead_link".to_string(),
+        };
+    };
+
+    let (kind, icon, color) = if entry.link_target.is_some() {
+        ("link", get_link_icon(config, metadata), "link".to_string())
+    } else if metadata.is_dir() {
+        (
+            "dir",
+            get_dir_icon(config, &entry.path),
+            get_dir_color_type(config, &entry.path).to_string(),
+        )
+    } else {
+        (
+            "file",
+            get_file_icon(config, Some(metadata), &entry.path, &entry.path),
+            get_file_color_type(config, metadata, &entry.path, &entry.path).to_string(),
+        )
+    };
+
+    EntryInfo {
+        name,
+        path,
+        kind,
+        target,
+        size: Some(get_file_size(metadata)),
+        mtime: metadata
+            .modified()
+            .ok()
+            .map(|time| DateTime::<Local>::from(time).to_rfc3339()),
+        btime: metadata
+            .created()
+            .ok()
+            .map(|time| DateTime::<Local>::from(time).to_rfc3339()),
+        permissions: Some(format_permissions(metadata)),
+        hash: entry.hash.clone(),
+        mime: entry.mime,
+        icon,
+        color,
+    }
+}
+
+fn build_entry_infos(config: &Config, entries: &[Entry]) -> Vec<EntryInfo> {
+    entries
+        .iter()
+        .filter(|entry| !entry.filtered)
+        .map(|entry| build_entry_info(config, entry))
+        .collect()
+}
+
+fn show_json(out: &mut impl Write, config: &Config, entries: &[Entry]) -> io::Result<()> {
+    writeln!(
+        out,
+        "{}",
+        serde_json::to_string_pretty(&build_entry_infos(config, entries))
+            .expect("Couldn't serialize entries")
+    )
+}
+
+/// Display one JSON object per line.
+fn show_jsonl(out: &mut impl Write, infos: &[EntryInfo]) -> io::Result<()> {
+    for info in infos {
+        writeln!(
+            out,
+            "{}",
+            serde_json::to_string(info).expect("Couldn't serialize entry")
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Display entries as JSON Lines and, with `-R`, the entries of their
+/// subdirectories. Each directory is written as soon as it's read, rather than
+/// after the whole tree has been collected.
+fn show_jsonl_tree(
+    out: &mut impl Write,
+    cmd: &Cmd,
+    config: &Config,
+    repo: Option<&git::Repository>,
+    entries: &[Entry],
+    depth: usize,
+) -> io::Result<()> {
+    show_jsonl(out, &build_entry_infos(config, entries))?;
+    out.flush()?;
+
+    if !cmd.recursive {
+        return Ok(());
+    }
+
+    for entry in entries {
+        if is_traversable(cmd, entry, depth) {
+            let children = read_children(cmd, config, repo, &entry.path);
+            show_jsonl_tree(out, cmd, config, repo, &children, depth + 1)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Display one entry per line, with tab-separated fields. Tabs, newlines, and
+/// backslashes in names are escaped, so every line has the same fields.
+fn show_tsv(out: &mut impl Write, infos: &[EntryInfo]) -> io::Result<()> {
+    let escape = |value: &str| {
+        value
+            .replace('\\', "\\\\")
+            .replace('\t', "\\t")
+            .replace('\n', "\\n")
+            .replace('\r', "\\r")
+    };
+
+    for info in infos {
+        writeln!(
+            out,
+            "{}\t{}\t{}\t{}\t{}\t{}",
+            escape(&info.name),
+            info.kind,
+            info.size.map(|size| size.to_string()).unwrap_or_default(),
+            info.mtime.as_deref().unwrap_or_default(),
+            info.btime.as_deref().unwrap_or_default(),
+            info.permissions.as_deref().unwrap_or_default()
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Get a field of an entry's JSON representation as text. Missing fields (e.g.
+/// the target of entries that are not links) are empty.
+fn get_info_field(value: &serde_json::Value, field: &str) -> String {
+    match &value[field] {
+        serde_json::Value::Null => String::new(),
+        serde_json::Value::String(value) => value.clone(),
+        value => value.to_string(),
+    }
+}
+
+/// Display each entry using a `--format` template. Names are colored and quoted
+/// like in the listing, and icons are left out when disabled.
+fn show_format(
+    out: &mut impl Write,
+    config: &Config,
+    template: &template::Template,
+    infos: &[EntryInfo],
+) -> io::Result<()> {
+    for info in infos {
+        let value = serde_json::to_value(info).expect("Couldn't serialize entry");
+
+        let line = template.render(
+            |field| match field {
+                "name" => format_with_color(
+                    config,
+                    format_name(config, Path::new(&info.name)),
+                    &info.color,
+                ),
+                "path" => format_name(config, Path::new(&info.path)),
+                "target" => info
+                    .target
+                    .as_ref()
+                    .map(|target| format_name(config, Path::new(target)))
+                    .unwrap_or_default(),
+                // Like in the listing, directories have no size.
+                "size" if info.kind == "dir" => String::new(),
+                "size" => info
+                    .size
+                    .map(|size| format_size(config, size))
+                    .unwrap_or_default(),
+                "bytes" => get_info_field(&value, "size"),
+                "icon" if !config.icons => String::new(),
+                field => get_info_field(&value, field),
+            },
+            visible_length,
+        );
+
+        writeln!(out, "{line}")?;
+    }
+
+    Ok(())
+}
+
+/// Display entries as CSV, with the same fields as the JSON output. Optional
+/// fields are only included when requested, so every row has the same fields.
+fn show_csv(out: &mut impl Write, cmd: &Cmd, infos: &[EntryInfo]) -> io::Result<()> {
+    let mut fields = vec![
+        "name",
+        "path",
+        "type",
+        "target",
+        "size",
+        "mtime",
+        "btime",
+        "permissions",
+    ];
+
+    if cmd.hash.is_some() {
+        fields.push("hash");
+    }
+
+    if cmd.mime {
+        fields.push("mime");
+    }
+
+    fields.extend(["icon", "color"]);
+
+    let mut writer = csv::Writer::from_writer(out);
+    writer.write_record(&fields)?;
+
+    for info in infos {
+        let value = serde_json::to_value(info).expect("Couldn't serialize entry");
+        let record = fields.iter().map(|field| get_info_field(&value, field));
+
+        writer.write_record(record)?;
+    }
+
+    writer.flush()
+}
+
+/// Display entries as an HTML page. Links are relative to the listed
+/// directory, so the page can be saved as its index.
+fn show_html(
+    out: &mut impl Write,
+    cmd: &Cmd,
+    config: &Config,
+    entries: &[Entry],
+    pwd: &Path,
+) -> io::Result<()> {
+    let items: Vec<html::Item> = entries
+        .iter()
+        .map(|entry| {
+            let info = build_entry_info(config, entry);
+            let relative_path = pathdiff::diff_paths(&entry.path, pwd)
+                .unwrap_or(entry.path.clone())
+                .to_string_lossy()
+                .to_string();
+            let is_dir = info.kind == "dir";
+
+            let href = if is_dir {
+                format!("{}/", html::encode_href(&relative_path))
+            } else {
+                html::encode_href(&relative_path)
+            };
+
+            html::Item {
+                name: relative_path,
+                href,
+                icon: config.icons.then_some(info.icon),
+                class: info.color,
+                size: info
+                    .size
+                    .filter(|_| !is_dir)
+                    .map(|size| format_size(config, size)),
+                modified: info
+                    .mtime
+                    .and_then(|mtime| DateTime::parse_from_rfc3339(&mtime).ok())
+                    .map(|mtime| mtime.format("%Y-%m-%d %H:%M").to_string()),
+            }
+        })
+        .collect();
+
+    let mut styles: Vec<(String, String)> = config
+        .colors
+        .iter()
+        .map(|(name, color)| {
+            let style = color.style();
+            let mut declarations = vec![];
+
+            if let Some(color) = style.color.as_deref().and_then(parse_color) {
+                declarations.push(format!("color: {};", html::css_color(color)));
+            }
+
+            if style.bold {
+                declarations.push("font-weight: bold;".to_string());
+            }
+
+            if style.italic {
+                declarations.push("font-style: italic;".to_string());
+            }
+
+            if style.underline {
+                declarations.push("text-decoration: underline;".to_string());
+            }
+
+            if style.dim {
+                declarations.push("opacity: 0.6;".to_string());
+            }
+
+            (name.clone(), declarations.join(" "))
+        })
+        .collect();
+    styles.sort();
+
+    let title = format!("Index of {}", cmd.path.as_deref().unwrap_or("."));
+
+    html::render(out, &title, &items, &styles)
+}
+
+/// Print the entries' paths separated by NUL bytes. Paths are written as they
+/// are, even when they're not valid UTF-8.
+fn show_print0(out: &mut impl Write, entries: &[Entry]) -> io::Result<()> {
+    for entry in entries {
+        #[cfg(unix)]
+        out.write_all(std::os::unix::ffi::OsStrExt::as_bytes(
+            entry.path.as_os_str(),
+        ))?;
+
+        #[cfg(not(unix))]
+        out.write_all(entry.path.to_string_lossy().as_bytes())?;
+
+        out.write_all(b"\0")?;
+    }
+
+    Ok(())
+}
+
+/// The columns displayed by the long listing format.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum Column {
+    Inode,
+    Octal,
+    #[value(alias = "perms")]
+    Permissions,
+    #[value(alias = "attrs")]
+    Flags,
+    #[value(alias = "user")]
+    Owner,
+    Group,
+    Context,
+    Size,
+    #[value(alias = "blocks")]
+    Allocated,
+    Media,
+    Bar,
+    #[value(name = "mtime", alias = "modified")]
+    Modified,
+    Hash,
+    #[value(alias = "type")]
+    Mime,
+    Name,
+}
+
+impl Column {
+    fn is_right_aligned(self) -> bool {
+        matches!(self, Column::Inode | Column::Size | Column::Allocated)
+    }
+
+    fn title(self) -> &'static str {
+        match self {
+            Column::Inode => "Inode",
+            Column::Octal => "Octal",
+            Column::Permissions => "Permissions",
+            Column::Flags => "Flags",
+            Column::Owner => "Owner",
+            Column::Group => "Group",
+            Column::Context => "Context",
+            Column::Size => "Size",
+            Column::Allocated => "Allocated",
+            Column::Media => "Media",
+            Column::Bar => "",
+            Column::Modified => "Modified",
+            Column::Hash => "Hash",
+            Column::Mime => "Type",
+            Column::Name => "Name",
+        }
+    }
+}
+
+fn get_long_columns(cmd: &Cmd) -> Vec<Column> {
+    if !cmd.fields.is_empty() {
+        return cmd.fields.clone();
+    }
+
+    let mut columns = vec![];
+
+    if cmd.inode {
+        columns.push(Column::Inode);
+    }
+
+    if cmd.octal {
+        columns.push(Column::Octal);
+    }
+
+    columns.push(Column::Permissions);
+
+    if cmd.flags {
+        columns.push(Column::Flags);
+    }
+
+    columns.extend([Column::Owner, Column::Group]);
+
+    if cmd.context {
+        columns.push(Column::Context);
+    }
+
+    columns.push(Column::Size);
+
+    if cmd.allocated {
+        columns.push(Column::Allocated);
+    }
+
+    if cmd.media_info {
+        columns.push(Column::Media);
+    }
+
+    if cmd.bars {
+        columns.push(Column::Bar);
+    }
+
+    columns.push(Column::Modified);
+
+    if cmd.hash.is_some() {
+        columns.push(Column::Hash);
+    }
+
+    if cmd.mime {
+        columns.push(Column::Mime);
+    }
+
+    columns.push(Column::Name);
+
+    columns
+}
+
+#[allow(clippy::too_many_arguments)]
+fn build_long_entry(
+    cmd: &Cmd,
+    config: &Config,
+    columns: &[Column],
+    entry: &Entry,
+    metadata: Option<&Metadata>,
+    name: &str,
+    largest: u64,
+    unit_width: usize,
+) -> Vec<String> {
+    let path = entry.path.as_path();
+
+    columns
+        .iter()
+        .map(|column| match (column, metadata) {
+            (Column::Name, _) => name.to_string(),
+            (Column::Context, _) => get_security_context(path).unwrap_or("?".to_string()),
+            (Column::Inode, _) => format_inode(path),
+            (Column::Bar, _) => format_size_bar(config, get_entry_size(entry), largest),
+            (Column::Hash, _) => match &entry.hash {
+                Some(hash) => format_with_color(config, hash.chars().take(12).collect(), "hash"),
+                None => "-".to_string(),
+            },
+            (Column::Media, _) => match &entry.media {
+                Some(media) => format_with_color(config, media.clone(), "media"),
+                None => String::new(),
+            },
+            (Column::Mime, _) => match entry.mime {
+                Some(mime) => format_with_color(config, mime.to_string(), "mime"),
+                None => "-".to_string(),
+            },
+            (_, None) => "?".to_string(),
+            (Column::Octal, Some(metadata)) => format_octal_permissions(metadata),
+            (Column::Permissions, Some(metadata)) => {
+                let mut permissions = format_permissions(metadata);
+
+                if config.highlight_world_writable {
+                    if let Some(color_type) = get_world_writable_color_type(metadata) {
+                        permissions = format_with_color(config, permissions, color_type);
+                    }
+                }
+
+                if xattr::has_any(path) {
+                    permissions.push('@');
+                }
+
+                permissions
+            }
+            (Column::Flags, Some(metadata)) => format_file_flags(config, path, metadata),
+            (Column::Owner, Some(metadata)) => {
+                let color_type = get_owner_color_type(path, metadata);
+                format_with_color(config, get_owner(cmd, path, metadata), color_type)
+            }
+            // The group is dimmed (or highlighted) along with the owner.
+            (Column::Group, Some(metadata)) => {
+                let color_type = match get_owner_color_type(path, metadata) {
+                    "owner" => "group",
+                    color_type => color_type,
+                };
+                format_with_color(config, get_group(cmd, metadata), color_type)
+            }
+            // Directory sizes are not meaningful, so we don't show them (same
+            // as the grid output).
+            (Column::Size, Some(metadata)) if metadata.is_dir() && entry.dir_size.is_some() => {
+                let size = format_size(config, entry.dir_size.unwrap_or_default());
+                let padding = " ".repeat(unit_width.saturating_sub(get_unit_width(&size)));
+
+                format!("{}{padding}", format_with_color(config, size, "file_size"))
+            }
+            (Column::Size, Some(metadata)) if metadata.is_dir() && config.count => {
+                format_dir_count(config, path)
+            }
+            (Column::Size, Some(metadata)) if metadata.is_dir() => "-".to_string(),
+            (Column::Size, Some(metadata)) => {
+                let size = format_file_size(config, metadata);
+                let padding = " ".repeat(unit_width.saturating_sub(get_unit_width(&size)));
+                let size = format!("{}{padding}", format_with_color(config, size, "file_size"));
+
+                match format_disk_size(config, metadata) {
+                    Some(disk_size) => format!("{size} {disk_size}"),
+                    None => size,
+                }
+            }
+            (Column::Allocated, Some(metadata)) => match get_allocated_size(metadata) {
+                Some(size) => format_with_color(config, format_size(config, size), "file_size"),
+                None => "-".to_string(),
+            },
+            (Column::Modified, Some(metadata)) => {
+                let time = format_time(cmd, config, get_time(metadata, cmd.time));
+
+                // The modification time is shown for entries without a
+                // creation time, so it's marked as such.
+                if cmd.time == TimeField::Created && metadata.created().is_err() {
+                    format!("{time}*")
+                } else {
+                    time
+                }
+            }
+        })
+        .collect()
+}
+
+/// Get one of the timestamps of an entry, when the platform has it. The
+/// creation time falls back to the modification time.
+fn get_time(metadata: &Metadata, field: TimeField) -> Option<SystemTime> {
+    match field {
+        TimeField::Modified => metadata.modified().ok(),
+        TimeField::Accessed => metadata.accessed().ok(),
+        TimeField::Changed => get_change_time(metadata),
+        TimeField::Created => metadata.created().or_else(|_| metadata.modified()).ok(),
+    }
+}
+
+#[cfg(unix)]
+fn get_change_time(metadata: &Metadata) -> Option<SystemTime> {
+    let seconds = u64::try_from(metadata.ctime()).ok()?;
+    let nanoseconds = u32::try_from(metadata.ctime_nsec()).ok()?;
+
+    SystemTime::UNIX_EPOCH.checked_add(time::Duration::new(seconds, nanoseconds))
+}
+
+#[cfg(windows)]
+fn get_change_time(_metadata: &Metadata) -> Option<SystemTime> {
+    None
+}
+
+fn format_time(cmd: &Cmd, config: &Config, time: Option<SystemTime>) -> String {
+    let Some(time) = time else {
+        return "-".to_string();
+    };
+
+    let time: DateTime<Local> = time.into();
+    let age = Local::now().signed_duration_since(time);
+
+    if cmd.time_style == TimeStyle::Relative {
+        let color_type = if age < Duration::hours(1) {
+            "time_hour"
+        } else if age < Duration::days(1) {
+            "time_day"
+        } else if age < Duration::weeks(1) {
+            "time_week"
+        } else {
+            "time_old"
+        };
+
+        return format_with_color(config, format_relative_time(age), color_type);
+    }
+
+    if let Some(time_format) = &config.time_format {
+        return time.format(time_format).to_string();
+    }
+
+    // Like `ls`, show the year instead of the time for entries that are
+    // older than six months (or that are in the future).
+    let format = if age > Duration::days(182) || age < Duration::zero() {
+        "%b %e  %Y"
+    } else {
+        "%b %e %H:%M"
+    };
+
+    time.format(format).to_string()
+}
+
+fn format_relative_time(age: Duration) -> String {
+    let seconds = age.num_seconds();
+    let units = [
+        ("year", 365 * 24 * 60 * 60),
+        ("month", 30 * 24 * 60 * 60),
+        ("week", 7 * 24 * 60 * 60),
+        ("day", 24 * 60 * 60),
+        ("hour", 60 * 60),
+        ("minute", 60),
+        ("second", 1),
+    ];
+
+    let Some((unit, value)) = units
+        .iter()
+        .map(|(unit, size)| (unit, seconds.abs() / size))
+        .find(|(_, value)| *value > 0)
+    else {
+        return "just now".to_string();
+    };
+
+    let plural = if value == 1 { "" } else { "s" };
+
+    if seconds < 0 {
+        format!("in {value} {unit}{plural}")
+    } else {
+        format!("{value} {unit}{plural} ago")
+    }
+}
+
+fn collect_entries(
+    cmd: &Cmd,
+    config: &Config,
+    repo: Option<&git::Repository>,
+    paths: &[PathBuf],
+) -> Vec<Entry> {
+    let ignore_list = |name: &str| -> Vec<String> {
+        config
+            .ignore
+            .get(name)
+            .unwrap_or_else(|| panic!("Couldn't get ignore.{name}"))
+            .iter()
+            .map(|s| s.to_lowercase())
+            .collect()
+    };
+    let folders = ignore_list("folders");
+    let files = ignore_list("files");
+
+    let mut git_ignores = HashMap::new();
+
+    let mut entries: Vec<Entry> = paths
+        .iter()
+        .map(|path| Entry {
+            path: path.clone(),
+            metadata: fs::metadata(path.clone())
+                .map_err(|_| Error::Metadata(path.clone()))
+                .ok(),
+            link_target: fs::read_link(path).ok(),
+            git_status: repo.map(|repo| repo.status(path)),
+            ignored: false,
+            filtered: false,
+            dir_size: None,
+            hash: None,
+            mime: None,
+            media: None,
+            archive: None,
+            capabilities: None,
+        })
+        .filter(|entry| cmd.directory || !entry.path.display().to_string().ends_with('.'))
+        .filter(|entry| ignore_entry(entry, &[], &[], &cmd.ignore_glob))
+        .filter_map(|mut entry| {
+            // Entries hidden by the configuration are still displayed with
+            // `-a`, but dimmed.
+            entry.ignored = !ignore_entry(&entry, &folders, &files, &[])
+                || (config.git_ignore && is_git_ignored(&entry, &mut git_ignores));
+
+            (cmd.all || !entry.ignored).then_some(entry)
+        })
+        .filter_map(|mut entry| {
+            let is_dir = entry.metadata.as_ref().is_some_and(Metadata::is_dir);
+            let is_traversed = is_dir && entry.link_target.is_none() && (cmd.tree || cmd.recursive);
+
+            entry.filtered = !filter_entry(cmd, &entry);
+
+            (!entry.filtered || is_traversed).then_some(entry)
+        })
+        .collect();
+
+    if cmd.du {
+        let mut progress = du::Progress::new();
+        let mut cache = du::Cache::load(!cmd.no_cache);
+
+        for entry in &mut entries {
+            let is_dir = entry.metadata.as_ref().is_some_and(Metadata::is_dir);
+
+            if is_dir && entry.link_target.is_none() && !entry.filtered {
+                entry.dir_size = Some(cache.dir_size(&entry.path, &mut progress));
+            }
+        }
+
+        progress.finish();
+        cache.save();
+    }
+
+    if cmd.archive_info {
+        for entry in &mut entries {
+            let is_file = entry.metadata.as_ref().is_some_and(Metadata::is_file);
+
+            if is_file && archive::is_archive(&entry.path) {
+                entry.archive = archive::read(&entry.path).ok().map(|members| {
+                    let files = members.iter().filter(|member| !member.is_dir);
+                    (files.clone().count(), files.map(|member| member.size).sum())
+                });
+            }
+        }
+    }
+
+    if cmd.caps {
+        for entry in &mut entries {
+            if entry.metadata.as_ref().is_some_and(Metadata::is_file) {
+                entry.capabilities = caps::get(&entry.path);
+            }
+        }
+    }
+
+    if cmd.mime || cmd.media_info {
+        for entry in &mut entries {
+            if !entry.metadata.as_ref().is_some_and(Metadata::is_file) {
+                continue;
+            }
+
+            if cmd.mime {
+                entry.mime = mime::detect(&entry.path);
+            }
+
+            if cmd.media_info {
+                entry.media = media::info(&entry.path);
+            }
+        }
+    }
+
+    if let Some(algorithm) = cmd.hash {
+        let is_file = |entry: &&mut Entry| entry.metadata.as_ref().is_some_and(Metadata::is_file);
+        let mut files: Vec<&mut Entry> = entries.iter_mut().filter(is_file).collect();
+        let paths: Vec<PathBuf> = files.iter().map(|entry| entry.path.clone()).collect();
+
+        for (entry, hash) in files.iter_mut().zip(hash::files(&paths, algorithm)) {
+            entry.hash = hash;
+        }
+    }
+
+    let sort_by = cmd.sort_by();
+    entries.sort_by(|a, b| compare_entries(a, b, sort_by, cmd.time));
+
+    if cmd.reverse {
+        entries.reverse();
+    }
+
+    // Sorting is stable, so entries keep their order within each group.
+    let is_dir = |entry: &Entry| entry.metadata.as_ref().is_some_and(Metadata::is_dir);
+
+    match cmd.group_dirs {
+        GroupDirs::First => entries.sort_by_key(|entry| !is_dir(entry)),
+        GroupDirs::Last => entries.sort_by_key(is_dir),
+        GroupDirs::None => {}
+    }
+
+    entries
+}
+
+// Ignore rules depend on the directory, so they're loaded once per directory.
+fn is_git_ignored(entry: &Entry, git_ignores: &mut HashMap<PathBuf, Option<git::Ignore>>) -> bool {
+    let dir = match entry.path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+
+    let is_dir = entry.metadata.as_ref().is_some_and(Metadata::is_dir);
+
+    git_ignores
+        .entry(dir.to_path_buf())
+        .or_insert_with(|| git::Ignore::load(dir))
+        .as_ref()
+        .is_some_and(|ignore| ignore.is_ignored(&entry.path, is_dir))
+}
+
+fn build_entry(config: &Config, entry: &Entry, path: &Path) -> String {
+    let (label, size, details) = build_entry_parts(config, entry, path);
+
+    [Some(label), size, details]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Build an entry's label, its size, and other details (e.g. an archive's
+/// contents) separately, so they can be aligned in columns.
+fn build_entry_parts(
+    config: &Config,
+    entry: &Entry,
+    path: &Path,
+) -> (String, Option<String>, Option<String>) {
+    let target = entry
+        .link_target
+        .as_ref()
+        .map(|target| format!(" -> {}", format_name(config, target)))
+        .unwrap_or_default();
+
+    let Some(metadata) = &entry.metadata else {
+        let classifier = if config.classify { "@" } else { "" };
+        let label = format!("{}{classifier}{target}", format_name(config, path));
+
+        let label = format_with_color(config, with_icon(config, "\u{f481}", &label), "dead_link");
+
+        return (label, None, None);
+    };
+
+    if entry.link_target.is_some() {
+        return (
+            build_link_entry(config, metadata, path, &target),
+            None,
+            None,
+        );
+    }
+
+    let allocated = get_allocated_size(metadata).filter(|_| config.allocated && metadata.is_file());
+    let size = allocated
+        .or_else(|| get_entry_size(entry))
+        .filter(|_| config.sizes)
+        .map(|size| format_with_color(config, format_size(config, size), "file_size"));
+
+    if metadata.is_dir() {
+        let mut label = build_dir_entry(config, metadata, path);
+
+        if config.count {
+            label = format!("{label} {}", format_dir_count(config, &entry.path));
+        }
+
+        (label, size, None)
+    } else {
+        let label = build_file_name(config, metadata, path, &entry.path);
+        let details: Vec<String> = [
+            format_disk_size(config, metadata).filter(|_| config.sizes),
+            entry
+                .archive
+                .map(|archive| format_archive_info(config, archive)),
+            format_capabilities(config, entry),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+
+        (
+            label,
+            size,
+            Some(details.join(" ")).filter(|details| !details.is_empty()),
+        )
+    }
+}
+
+/// Describe an archive's contents, like `(3,410 files, 4.8GB)`.
+fn format_archive_info(config: &Config, (files, size): (usize, u64)) -> String {
+    let noun = if files == 1 { "file" } else { "files" };
+    let info = format!(
+        "({} {noun}, {})",
+        format_count(config, files as u64),
+        format_size(config, size)
+    );
+
+    format_with_color(config, info, "archive_info")
+}
+
+/// Format the flags of an entry (e.g. `ia` for immutable and append-only), or
+/// `-` when it has none. Only files and directories are opened to read them.
+fn format_file_flags(config: &Config, path: &Path, metadata: &Metadata) -> String {
+    if !metadata.is_file() && !metadata.is_dir() {
+        return "-".to_string();
+    }
+
+    match flags::get(path) {
+        Some(flags) if flags.is_empty() => "-".to_string(),
+        Some(flags) => format_with_color(config, flags, "file_flags"),
+        None => "?".to_string(),
+    }
+}
+
+/// Describe the capabilities of an executable, like `[cap_net_raw+ep]`. They
+/// use a warning color, as they grant privileges that are easy to miss.
+fn format_capabilities(config: &Config, entry: &Entry) -> Option<String> {
+    let capabilities = entry.capabilities.clone()?;
+
+    Some(format_with_color(
+        config,
+        format!("[{capabilities}]"),
+        "capabilities",
+    ))
+}
+
+/// Format a number with thousands separators, like `3,410`. The separator
+/// comes from the configuration, or the locale when it's not set.
+fn format_count(config: &Config, count: u64) -> String {
+    let separator = config
+        .thousands_separator
+        .clone()
+        .unwrap_or_else(get_locale_thousands_separator);
+    let digits = count.to_string();
+    let mut output = String::new();
+
+    for (index, digit) in digits.chars().enumerate() {
+        if index > 0 && (digits.len() - index).is_multiple_of(3) {
+            output.push_str(&separator);
+        }
+
+        output.push(digit);
+    }
+
+    output
+}
+
+/// The thousands separator used by the locale's language (as set by `LC_ALL`,
+/// `LC_NUMERIC`, or `LANG`), like `.` for German or a space for French.
+fn get_locale_thousands_separator() -> String {
+    let locale = ["LC_ALL", "LC_NUMERIC", "LANG"]
+        .iter()
+        .find_map(|name| std::env::var(name).ok().filter(|value| !value.is_empty()))
+        .unwrap_or_default();
+    let language = locale.split(['_', '.', '@']).next().unwrap_or_default();
+
+    let separator = match (language, locale.get(language.len()..language.len() + 3)) {
+        ("de" | "it", Some("_CH")) => "'",
+        ("da" | "de" | "el" | "es" | "id" | "it" | "nl" | "pt" | "tr", _) => ".",
+        ("cs" | "fi" | "fr" | "hu" | "nb" | "pl" | "ru" | "sk" | "sv" | "uk", _) => "\u{202f}",
+        _ => ",",
+    };
+
+    separator.to_string()
+}
+
+/// The size of an entry, as displayed in the listing. Directories only have a
+/// size when it has been computed with `--du`.
+fn get_entry_size(entry: &Entry) -> Option<u64> {
+    match &entry.metadata {
+        Some(metadata) if metadata.is_dir() => entry.dir_size,
+        Some(metadata) => Some(get_file_size(metadata)),
+        None => None,
+    }
+}
+
+/// Draw a bar proportional to the given size, with eighths of a cell of
+/// precision. Entries without a size get an empty bar, so columns still line
+/// up.
+fn format_size_bar(config: &Config, size: Option<u64>, largest: u64) -> String {
+    const WIDTH: usize = 10;
+    const BLOCKS: [char; 8] = ['▏', '▎', '▍', '▌', '▋', '▊', '▉', '█'];
+
+    let eighths = match size {
+        Some(size) if largest > 0 => (size as f64 / largest as f64 * (WIDTH * 8) as f64).round(),
+        _ => 0.0,
+    } as usize;
+
+    let (full, partial) = (eighths / 8, eighths % 8);
+    let mut bar = "█".repeat(full);
+
+    if partial > 0 {
+        bar.push(BLOCKS[partial - 1]);
+    }
+
+    let padding = " ".repeat(WIDTH - bar.chars().count());
+
+    format!("{}{padding}", format_with_color(config, bar, "size_bar"))
+}
+
+/// Count the entries inside a directory. Directories that can't be read (e.g.
+/// because of permissions) show `?` instead.
+fn format_dir_count(config: &Config, path: &Path) -> String {
+    let count = fs::read_dir(path).map_or("?".to_string(), |dir| dir.count().to_string());
+
+    format_with_color(config, count, "dir_count")
+}
+
+/// SGR sequences, which set colors and other text attributes.
+static SGR: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\x1b\[[0-9;]*m").unwrap());
+
+/// Thumbnails, using either the kitty or the iTerm2 protocol.
+static THUMBNAIL: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(\x1b_G[^\x1b]*\x1b\\)+|\x1b\]1337;File=[^\x07]*\x07").unwrap());
+
+/// SGR (colors) and OSC 8 (hyperlinks) sequences, which take no space.
+static ANSI_ESCAPE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\x1b\[[0-9;]*m|\x1b\]8;[^\x1b\x07]*(\x1b\\|\x07)").unwrap());
+
+/// Any escape sequence used in labels: thumbnails, colors, and hyperlinks.
+static ESCAPE_SEQUENCE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(&format!("{}|{}", THUMBNAIL.as_str(), ANSI_ESCAPE.as_str())).unwrap()
+});
+
+/// Replace the label's colors with the `ignored` color, for entries that are
+/// only displayed because of `-a`.
+fn with_ignored_style(config: &Config, entry: &Entry, label: String) -> String {
+    if !entry.ignored || !config.colorize {
+        return label;
+    }
+
+    format_with_color(config, SGR.replace_all(&label, "").to_string(), "ignored")
+}
+
+fn with_git_status(config: &Config, entry: &Entry, label: String) -> String {
+    let Some(status) = entry.git_status else {
+        return label;
+    };
+
+    let marker = format_with_color(config, status.marker().to_string(), status.color_type());
+
+    format!("{marker} {label}")
+}
+
+/// Wrap the label with an OSC 8 hyperlink pointing to the entry, so it can be
+/// clicked on terminals that support it.
+fn with_hyperlink(config: &Config, entry: &Entry, label: String) -> String {
+    if !config.hyperlink {
+        return label;
+    }
+
+    let Ok(path) = std::path::absolute(&entry.path) else {
+        return label;
+    };
+
+    let path = path.to_string_lossy().replace('\\', "/");
+    let path = if path.starts_with('/') {
+        path
+    } else {
+        format!("/{path}")
+    };
+
+    // Percent-encode everything but unreserved characters and separators, so
+    // names with spaces (and other special characters) are valid URLs.
+    let url: String = path
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' | b':' => {
+                char::from(byte).to_string()
+            }
+            _ => format!("%{byte:02X}"),
+        })
+        .collect();
+
+    format!("\x1b]8;;file://{url}\x1b\\{label}\x1b]8;;\x1b\\")
+}
+
+fn is_traversable(cmd: &Cmd, entry: &Entry, depth: usize) -> bool {
+    let is_dir = entry.metadata.as_ref().is_some_and(Metadata::is_dir);
+    let is_symlink = entry.link_target.is_some();
+    let within_depth = cmd.depth.is_none_or(|max_depth| depth < max_depth);
+
+    // Symlinked directories are not followed, so we don't end up in a loop.
+    is_dir && !is_symlink && within_depth
+}
+
+/// Whether a directory that doesn't match the filters has entries that do, so
+/// `--tree` displays it to show where they are. Results are kept in `searched`,
+/// so each directory is only searched once.
+fn has_matches(
+    cmd: &Cmd,
+    config: &Config,
+    repo: Option<&git::Repository>,
+    entry: &Entry,
+    depth: usize,
+    searched: &mut HashMap<PathBuf, bool>,
+) -> bool {
+    if !is_traversable(cmd, entry, depth) {
+        return false;
+    }
+
+    if let Some(found) = searched.get(&entry.path) {
+        return *found;
+    }
+
+    let found = read_children(cmd, config, repo, &entry.path)
+        .iter()
+        .any(|child| !child.filtered || has_matches(cmd, config, repo, child, depth + 1, searched));
+
+    searched.insert(entry.path.clone(), found);
+
+    found
+}
+
+/// Replace directories with their contents, recursively.
+fn collect_descendants(
+    cmd: &Cmd,
+    config: &Config,
+    repo: Option<&git::Repository>,
+    entries: Vec<Entry>,
+    depth: usize,
+) -> Vec<Entry> {
+    entries
+        .into_iter()
+        .flat_map(|entry| {
+            if is_traversable(cmd, &entry, depth) {
+                let children = read_children(cmd, config, repo, &entry.path);
+                collect_descendants(cmd, config, repo, children, depth + 1)
+            } else if entry.filtered {
+                vec![]
+            } else {
+                vec![entry]
+            }
+        })
+        .collect()
+}
+
+fn read_children(
+    cmd: &Cmd,
+    config: &Config,
+    repo: Option<&git::Repository>,
+    dir: &Path,
+) -> Vec<Entry> {
+    let children: Vec<PathBuf> = fs::read_dir(dir)
+        .map(|dir| {
+            dir.filter_map(Result::ok)
+                .map(|child| child.path())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    collect_entries(cmd, config, repo, &children)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn show_tree(
+    out: &mut impl Write,
+    cmd: &Cmd,
+    config: &Config,
+    repo: Option<&git::Repository>,
+    entries: &[Entry],
+    pwd: &Path,
+    depth: usize,
+    guides: &str,
+    searched: &mut HashMap<PathBuf, bool>,
+) -> io::Result<()> {
+    // Directories that don't match the filters are only displayed when they
+    // lead to entries that do.
+    let entries: Vec<&Entry> = entries
+        .iter()
+        .filter(|entry| !entry.filtered || has_matches(cmd, config, repo, entry, depth, searched))
+        .collect();
+
+    for (index, entry) in entries.iter().enumerate() {
+        let last = index == entries.len() - 1;
+
+        // Root entries are displayed just like the regular listing; nested
+        // entries only show their names, as the tree already tells where they
+        // are.
+        let (path, connector) = if depth == 1 {
+            let path = pathdiff::diff_paths(&entry.path, pwd).unwrap_or(entry.path.clone());
+            (path, "")
+        } else {
+            let path = PathBuf::from(entry.path.file_name().unwrap_or_default());
+            (path, if last { "└── " } else { "├── " })
+        };
+
+        let label = with_ignored_style(config, entry, build_entry(config, entry, &path));
+
+        writeln!(
+            out,
+            "  {guides}{connector}{}",
+            with_git_status(config, entry, with_hyperlink(config, entry, label))
+        )?;
+
+        if !is_traversable(cmd, entry, depth) {
+            continue;
+        }
+
+        let children = read_children(cmd, config, repo, &entry.path);
+
+        let guides = match (depth, last) {
+            (1, _) => String::new(),
+            (_, true) => format!("{guides}    "),
+            (_, false) => format!("{guides}│   "),
+        };
+
+        show_tree(
+            out,
+            cmd,
+            config,
+            repo,
+            &children,
+            pwd,
+            depth + 1,
+            &guides,
+            searched,
+        )?;
+    }
+
+    Ok(())
+}
+
+fn show_entries(
+    out: &mut impl Write,
+    cmd: &Cmd,
+    config: &Config,
+    repo: Option<&git::Repository>,
+    paths: &[PathBuf],
+    pwd: &Path,
+) -> io::Result<()> {
+    let mut entries = collect_entries(cmd, config, repo, paths);
+
+    if cmd.stats {
+        if cmd.recursive {
+            entries = collect_descendants(cmd, config, repo, entries, 1);
+        }
+
+        return show_stats(out, cmd, config, &entries);
+    }
+
+    if let Some(count) = cmd.largest {
+        // With `-R`, the largest files are searched in subdirectories too, and
+        // displayed in a single listing, relative to the listed directory.
+        let dir = entries
+            .first()
+            .and_then(|entry| entry.path.parent())
+            .map(Path::to_path_buf);
+        let pwd = match &dir {
+            Some(dir) if cmd.recursive => dir,
+            _ => pwd,
+        };
+
+        if cmd.recursive {
+            entries = collect_descendants(cmd, config, repo, entries, 1);
+        }
+
+        entries.sort_by(|a, b| compare_entries(a, b, SortBy::Size, cmd.time));
+        entries.truncate(count);
+
+        if cmd.json {
+            return show_json(out, config, &entries);
+        }
+
+        if cmd.jsonl {
+            return show_jsonl(out, &build_entry_infos(config, &entries));
+        }
+
+        if let Some(template) = &cmd.format {
+            return show_format(out, config, template, &build_entry_infos(config, &entries));
+        }
+
+        if cmd.tsv {
+            return show_tsv(out, &build_entry_infos(config, &entries));
+        }
+
+        if cmd.csv {
+            return show_csv(out, cmd, &build_entry_infos(config, &entries));
+        }
+
+        if cmd.print0 {
+            return show_print0(out, &entries);
+        }
+
+        if cmd.html {
+            return show_html(out, cmd, config, &entries, pwd);
+        }
+
+        return show_listing(out, cmd, config, &entries, pwd);
+    }
+
+    if cmd.json {
+        return show_json(out, config, &entries);
+    }
+
+    if cmd.jsonl {
+        return show_jsonl_tree(out, cmd, config, repo, &entries, 1);
+    }
+
+    if let Some(template) = &cmd.format {
+        return show_format(out, config, template, &build_entry_infos(config, &entries));
+    }
+
+    if cmd.tsv {
+        return show_tsv(out, &build_entry_infos(config, &entries));
+    }
+
+    if cmd.csv {
+        return show_csv(out, cmd, &build_entry_infos(config, &entries));
+    }
+
+    if cmd.html {
+        return show_html(out, cmd, config, &entries, pwd);
+    }
+
+    if cmd.print0 {
+        if cmd.recursive {
+            entries = collect_descendants(cmd, config, repo, entries, 1);
+        }
+
+        return show_print0(out, &entries);
+    }
+
+    if cmd.tree {
+        show_tree(
+            out,
+            cmd,
+            config,
+            repo,
+            &entries,
+            pwd,
+            1,
+            "",
+            &mut HashMap::new(),
+        )?;
+    } else {
+        show_listing(out, cmd, config, &entries, pwd)?;
+
+        if cmd.recursive {
+            show_subdirectories(out, cmd, config, repo, &entries, pwd, 1)?;
+        }
+    }
+
+    if config.summary {
+        show_summary(out, config, &entries)?;
+    }
+
+    Ok(())
+}
+
+/// Display how many directories and files were listed, and their total size.
+/// Only the listed entries are counted, not the contents of subdirectories.
+fn show_summary(out: &mut impl Write, config: &Config, entries: &[Entry]) -> io::Result<()> {
+    let entries = entries.iter().filter(|entry| !entry.filtered);
+    let is_dir = |entry: &&Entry| entry.metadata.as_ref().is_some_and(Metadata::is_dir);
+    let dirs = entries.clone().filter(is_dir).count();
+    let files = entries.clone().count() - dirs;
+    let size: u64 = entries.filter_map(get_entry_size).sum();
+
+    let plural = |count: usize, name: &str| {
+        if count == 1 {
+            format!("{count} {name}")
+        } else {
+            format!("{count} {name}s")
+        }
+    };
+
+    writeln!(
+        out,
+        "  {}, {}, {}",
+        plural(dirs, "dir"),
+        plural(files, "file"),
+        format_with_color(config, format_size(config, size), "file_size")
+    )
+}
+
+/// List the members of an archive as if it were a directory. With `-R` (or
+/// `--tree`), all members are listed at once, along with their paths.
+fn show_archive(
+    out: &mut impl Write,
+    cmd: &Cmd,
+    config: &Config,
+    path: &Path,
+) -> Result<(), Error> {
+    let members = archive::read(path).map_err(|error| Error::Archive(path.to_path_buf(), error))?;
+
+    let mut members = if cmd.recursive || cmd.tree {
+        members
+    } else {
+        archive::top_level(members)
+    };
+
+    let name = |member: &archive::Member| member.path.to_string_lossy().to_lowercase();
+
+    members.sort_by(|a, b| {
+        let ordering = match cmd.sort_by() {
+            SortBy::Size => b.size.cmp(&a.size),
+            SortBy::Time => b.modified.cmp(&a.modified),
+            _ => Ordering::Equal,
+        };
+
+        ordering.then_with(|| name(a).cmp(&name(b)))
+    });
+
+    if cmd.reverse {
+        members.reverse();
+    }
+
+    match cmd.group_dirs {
+        GroupDirs::First => members.sort_by_key(|member| !member.is_dir),
+        GroupDirs::Last => members.sort_by_key(|member| member.is_dir),
+        GroupDirs::None => {}
+    }
+
+    if cmd.json || cmd.jsonl || cmd.tsv || cmd.csv || cmd.format.is_some() {
+        let infos: Vec<EntryInfo> = members
+            .iter()
+            .map(|member| build_member_info(config, member))
+            .collect();
+
+        if cmd.jsonl {
+            return Ok(show_jsonl(out, &infos)?);
+        }
+
+        if let Some(template) = &cmd.format {
+            return Ok(show_format(out, config, template, &infos)?);
+        }
+
+        if cmd.tsv {
+            return Ok(show_tsv(out, &infos)?);
+        }
+
+        if cmd.csv {
+            return Ok(show_csv(out, cmd, &infos)?);
+        }
+
+        writeln!(
+            out,
+            "{}",
+            serde_json::to_string_pretty(&infos).expect("Couldn't serialize entries")
+        )?;
+
+        return Ok(());
+    }
+
+    if !cmd.long {
+        let list: Vec<String> = members
+            .iter()
+            .map(|member| build_member_entry(config, member))
+            .collect();
+        let list = truncate_items(cmd, list);
+
+        if cmd.single_column {
+            for item in list {
+                writeln!(out, "  {item}")?;
+            }
+        } else {
+            display_in_columns(out, cmd, config, &list)?;
+        }
+
+        return Ok(());
+    }
+
+    let columns = [
+        Column::Permissions,
+        Column::Size,
+        Column::Modified,
+        Column::Name,
+    ];
+    let mut rows: Vec<Vec<String>> = vec![];
+
+    if cmd.header && !members.is_empty() {
+        rows.push(
+            columns
+                .iter()
+                .map(|column| format_with_color(config, column.title().to_string(), "header"))
+                .collect(),
+        );
+    }
+
+    for member in &members {
+        let size = if member.is_dir {
+            "-".to_string()
+        } else {
+            format_with_color(config, format_size(config, member.size), "file_size")
+        };
+
+        rows.push(vec![
+            member
+                .mode
+                .map_or("-".to_string(), |mode| format_member_mode(member, mode)),
+            size,
+            format_time(cmd, config, member.modified),
+            build_member_name(config, member),
+        ]);
+    }
+
+    display_in_table(out, config, &rows, &[1])?;
+
+    Ok(())
+}
+
+fn format_member_mode(member: &archive::Member, mode: u32) -> String {
+    let kind = match (member.is_dir, &member.link_target) {
+        (true, _) => 'd',
+        (_, Some(_)) => 'l',
+        _ => '-',
+    };
+
+    format_mode(kind, mode)
+}
+
+/// Build an archive member's label, like `build_entry` does for files on disk.
+fn build_member_entry(config: &Config, member: &archive::Member) -> String {
+    let name = build_member_name(config, member);
+
+    if member.is_dir || member.link_target.is_some() || !config.sizes {
+        return name;
+    }
+
+    format!(
+        "{name} {}",
+        format_with_color(config, format_size(config, member.size), "file_size")
+    )
+}
+
+fn build_member_name(config: &Config, member: &archive::Member) -> String {
+    let path = &member.path;
+    let (icon, color_type) = get_member_style(config, member);
+
+    let label = match (&member.link_target, member.is_dir) {
+        (Some(target), _) => format!(
+            "{} -> {}",
+            format_name(config, path),
+            format_name(config, target)
+        ),
+        (None, true) => format!("{}/", format_name(config, path)),
+        (None, false) => format_name(config, path),
+    };
+
+    format_with_color(config, with_icon(config, &icon, &label), color_type)
+}
+
+/// Resolve the icon and color of an archive member. Members can't be read
+/// without extracting them, so their type is only detected by name.
+fn get_member_style<'a>(config: &'a Config, member: &archive::Member) -> (String, &'a str) {
+    let path = &member.path;
+
+    if member.link_target.is_some() {
+        let icon = resolve_icon(
+            &config.kinds,
+            &config.aliases,
+            "\u{f481}",
+            vec!["file_symlink".to_string()],
+        );
+
+        return (icon, "link");
+    }
+
+    if member.is_dir {
+        return (get_dir_icon(config, path), get_dir_color_type(config, path));
+    }
+
+    let icon = get_file_icon(config, None, path, Path::new(""));
+    let hidden = path
+        .file_name()
+        .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+
+    let color_type = if member
+        .mode
+        .is_some_and(|mode| mode & 0o111 != 0 && mode & 0o6000 != 0)
+    {
+        "warning"
+    } else if member.mode.is_some_and(|mode| mode & 0o111 != 0) {
+        "executable_file"
+    } else if let Some(color_type) = match_color_rule(config, path) {
+        color_type
+    } else if hidden {
+        "hidden"
+    } else {
+        "file"
+    };
+
+    (icon, color_type)
+}
+
+fn build_member_info(config: &Config, member: &archive::Member) -> EntryInfo {
+    let (icon, color) = get_member_style(config, member);
+    let kind = match (member.is_dir, &member.link_target) {
+        (true, _) => "dir",
+        (_, Some(_)) => "link",
+        _ => "file",
+    };
+
+    EntryInfo {
+        name: member
+            .path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string(),
+        path: member.path.display().to_string(),
+        kind,
+        target: member
+            .link_target
+            .as_ref()
+            .map(|target| target.display().to_string()),
+        size: Some(member.size),
+        mtime: member
+            .modified
+            .map(|time| DateTime::<Local>::from(time).to_rfc3339()),
+        btime: None,
+        permissions: member.mode.map(|mode| format_member_mode(member, mode)),
+        hash: None,
+        mime: None,
+        icon,
+        color: color.to_string(),
+    }
+}
+
+/// The files with a given extension, as displayed by `--stats`.
+#[derive(Serialize, Debug)]
+struct ExtensionStats {
+    extension: String,
+    files: usize,
+    size: u64,
+}
+
+/// Display a table with how many files there are for each extension, and
+/// their total size, largest first. Directories are not counted.
+fn show_stats(
+    out: &mut impl Write,
+    cmd: &Cmd,
+    config: &Config,
+    entries: &[Entry],
+) -> io::Result<()> {
+    let mut stats: Vec<ExtensionStats> = vec![];
+
+    for entry in entries {
+        let Some(metadata) = entry
+            .metadata
+            .as_ref()
+            .filter(|metadata| !metadata.is_dir())
+        else {
+            continue;
+        };
+
+        let extension = entry
+            .path
+            .extension()
+            .map(|ext| format!(".{}", ext.to_string_lossy().to_lowercase()))
+            .unwrap_or_default();
+
+        let index = match stats.iter().position(|stat| stat.extension == extension) {
+            Some(index) => index,
+            None => {
+                stats.push(ExtensionStats {
+                    extension,
+                    files: 0,
+                    size: 0,
+                });
+                stats.len() - 1
+            }
+        };
+
+        stats[index].files += 1;
+        stats[index].size += get_file_size(metadata);
+    }
+
+    stats.sort_by(|a, b| {
+        b.size
+            .cmp(&a.size)
+            .then_with(|| a.extension.cmp(&b.extension))
+    });
+
+    if cmd.json {
+        return writeln!(
+            out,
+            "{}",
+            serde_json::to_string_pretty(&stats).expect("Couldn't serialize stats")
+        );
+    }
+
+    if stats.is_empty() {
+        return Ok(());
+    }
+
+    let header = ["Extension", "Files", "Size"]
+        .iter()
+        .map(|title| format_with_color(config, title.to_string(), "header"))
+        .collect();
+    let mut rows: Vec<Vec<String>> = vec![header];
+
+    for stat in &stats {
+        // Files are matched by extension only, so the icon is the same used
+        // for any file with that extension.
+        let icon = resolve_icon(
+            &config.files,
+            &config.aliases,
+            "\u{ea7b}",
+            vec![stat.extension.clone(), "file".to_string()],
+        );
+        let label = if stat.extension.is_empty() {
+            "(none)"
+        } else {
+            &stat.extension
+        };
+
+        rows.push(vec![
+            format_with_color(config, with_icon(config, &icon, label), "file"),
+            stat.files.to_string(),
+            format_with_color(config, format_size(config, stat.size), "file_size"),
+        ]);
+    }
+
+    display_in_table(out, config, &rows, &[1, 2])
+}
+
+/// Display the sets of files with identical contents inside `dir`, wasting the
+/// most space first. Symlinks are not considered duplicates of their targets.
+fn show_dupes(
+    out: &mut impl Write,
+    cmd: &Cmd,
+    config: &Config,
+    dir: &Path,
+    min_size: u64,
+) -> Result<(), Error> {
+    if !dir.is_dir() {
+        return Err(Error::PathNotFound(dir.display().to_string()));
+    }
+
+    let children = read_children(cmd, config, None, dir);
+    let entries = collect_descendants(cmd, config, None, children, 1);
+
+    let files = entries
+        .iter()
+        .filter(|entry| entry.link_target.is_none())
+        .filter_map(|entry| Some((entry.path.clone(), get_entry_size(entry)?)))
+        .filter(|(_, size)| *size >= min_size)
+        .collect();
+
+    let mut groups: Vec<Vec<&Entry>> = dupes::find(files)
+        .into_iter()
+        .map(|paths| {
+            let mut group: Vec<&Entry> = entries
+                .iter()
+                .filter(|entry| paths.contains(&entry.path))
+                .collect();
+            group.sort_by(|a, b| compare_entries(a, b, SortBy::Name, cmd.time));
+            group
+        })
+        .collect();
+
+    let size = |group: &[&Entry]| group.first().and_then(|entry| get_entry_size(entry));
+    let wasted = |group: &[&Entry]| size(group).unwrap_or_default() * (group.len() as u64 - 1);
+
+    groups.sort_by(|a, b| {
+        wasted(b)
+            .cmp(&wasted(a))
+            .then_with(|| a[0].path.cmp(&b[0].path))
+    });
+
+    for (index, group) in groups.iter().enumerate() {
+        if index > 0 {
+            writeln!(out)?;
+        }
+
+        let title = format!(
+            "{} files, {} each",
+            group.len(),
+            format_size(config, size(group).unwrap_or_default())
+        );
+        writeln!(out, "{}", format_with_color(config, title, "header"))?;
+
+        for entry in group {
+            let path = pathdiff::diff_paths(&entry.path, dir).unwrap_or(entry.path.clone());
+            let label = with_ignored_style(config, entry, build_entry(config, entry, &path));
+
+            writeln!(out, "  {}", with_hyperlink(config, entry, label))?;
+        }
+    }
+
+    Ok(())
+}
+
+fn show_subdirectories(
+    out: &mut impl Write,
+    cmd: &Cmd,
+    config: &Config,
+    repo: Option<&git::Repository>,
+    entries: &[Entry],
+    root: &Path,
+    depth: usize,
+) -> io::Result<()> {
+    for entry in entries {
+        if !is_traversable(cmd, entry, depth) {
+            continue;
+        }
+
+        let children = read_children(cmd, config, repo, &entry.path);
+
+        // Directories that don't match the filters only get their own section
+        // when they have entries that do.
+        if !entry.filtered || children.iter().any(|child| !child.filtered) {
+            let header = pathdiff::diff_paths(&entry.path, root).unwrap_or(entry.path.clone());
+
+            writeln!(out)?;
+            writeln!(
+                out,
+                "{}",
+                format_with_color(config, format!("{}:", format_name(config, &header)), "dir")
+            )?;
+
+            show_listing(out, cmd, config, &children, &entry.path)?;
+        }
+
+        show_subdirectories(out, cmd, config, repo, &children, root, depth + 1)?;
+    }
+
+    Ok(())
+}
+
+fn show_listing(
+    out: &mut impl Write,
+    cmd: &Cmd,
+    config: &Config,
+    entries: &[Entry],
+    pwd: &Path,
+) -> io::Result<()> {
+    // Directories that don't match the filters are traversed with `-R`, but not
+    // listed.
+    let entries: Vec<&Entry> = entries.iter().filter(|entry| !entry.filtered).collect();
+    let mut items: Vec<(&Entry, String, Option<String>, Option<String>)> = vec![];
+    let mut rows: Vec<Vec<String>> = vec![];
+    let mut inodes: Vec<String> = vec![];
+    let columns = get_long_columns(cmd);
+    let largest = entries
+        .iter()
+        .copied()
+        .filter_map(get_entry_size)
+        .max()
+        .unwrap_or_default();
+
+    // Sizes are right-aligned, and their units are padded to the same width,
+    // so the numbers line up too (e.g. `5B ` above `1.5KB`).
+    let unit_width = entries
+        .iter()
+        .copied()
+        .filter_map(get_entry_size)
+        .map(|size| get_unit_width(&format_size(config, size)))
+        .max()
+        .unwrap_or_default();
+
+    for entry in entries {
+        let relative_path = pathdiff::diff_paths(&entry.path, pwd).unwrap_or(entry.path.clone());
+
+        if !cmd.long {
+            let (label, size, mut details) = build_entry_parts(config, entry, &relative_path);
+
+            // Entries are not aligned in the grid, so there's no need to draw
+            // an empty bar for entries without a size.
+            if let Some(size) = get_entry_size(entry).filter(|_| cmd.bars) {
+                let bar = format_size_bar(config, Some(size), largest);
+                let bar = bar.trim_end();
+
+                details = Some(match details {
+                    Some(details) => format!("{details} {bar}"),
+                    None => bar.to_string(),
+                });
+            }
+
+            items.push((entry, label, size, details));
+
+            if cmd.inode {
+                inodes.push(format_inode(&entry.path));
+            }
+
+            continue;
+        }
+
+        let name = match (&entry.metadata, &entry.link_target) {
+            (Some(metadata), None) if metadata.is_dir() => {
+                build_dir_entry(config, metadata, &relative_path)
+            }
+            (Some(metadata), None) => {
+                let name = build_file_name(config, metadata, &relative_path, &entry.path);
+                let archive = entry
+                    .archive
+                    .map(|archive| format_archive_info(config, archive));
+
+                [Some(name), archive, format_capabilities(config, entry)]
+                    .into_iter()
+                    .flatten()
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            }
+            _ => build_entry(config, entry, &relative_path),
+        };
+        let name = with_ignored_style(config, entry, name);
+        let name = with_git_status(config, entry, with_hyperlink(config, entry, name));
+
+        // Like `ls`, symlinks are described by their own metadata, rather than
+        // their target's.
+        let metadata = if entry.link_target.is_some() || entry.metadata.is_none() {
+            fs::symlink_metadata(&entry.path).ok()
+        } else {
+            entry.metadata.clone()
+        };
+
+        rows.push(build_long_entry(
+            cmd,
+            config,
+            &columns,
+            entry,
+            metadata.as_ref(),
+            &name,
+            largest,
+            unit_width,
+        ));
+
+        // Like `ls -l@`, attributes are listed under the entry, in the last
+        // column.
+        if cmd.xattrs {
+            for attribute in xattr::list(&entry.path) {
+                let mut row = vec![String::new(); columns.len()];
+                row[columns.len() - 1] = format!(
+                    "    {} {}",
+                    attribute.name,
+                    format_with_color(config, attribute.size.to_string(), "file_size")
+                );
+
+                rows.push(row);
+            }
+        }
+    }
+
+    let mut list = build_list_items(config, cmd.single_column, items);
+
+    if cmd.inode {
+        let width = inodes.iter().map(String::len).max().unwrap_or_default();
+
+        list = inodes
+            .iter()
+            .zip(list)
+            .map(|(inode, item)| format!("{inode:>width$} {item}"))
+            .collect();
+    }
+
+    if cmd.long && cmd.header && !rows.is_empty() {
+        let header = columns
+            .iter()
+            .map(|column| match column {
+                Column::Modified => cmd.time.title(),
+                _ => column.title(),
+            })
+            .map(|title| format_with_color(config, title.to_string(), "header"))
+            .collect();
+
+        rows.insert(0, header);
+    }
+
+    if cmd.long {
+        let right_aligned: Vec<usize> = columns
+            .iter()
+            .enumerate()
+            .filter(|(_, column)| column.is_right_aligned())
+            .map(|(index, _)| index)
+            .collect();
+
+        display_in_table(out, config, &rows, &right_aligned)
+    } else if cmd.single_column {
+        for item in truncate_items(cmd, list) {
+            writeln!(out, "  {item}")?;
+        }
+
+        Ok(())
+    } else if cmd.commas {
+        display_with_commas(out, cmd, &list)
+    } else {
+        display_in_columns(out, cmd, config, &truncate_items(cmd, list))
+    }
+}
+
+/// Join the parts of each entry into the items displayed by the grid. With
+/// `-1`, labels are padded and sizes are right-aligned, with their units padded
+/// to the same width, so both numbers and units line up. This needs the widths
+/// of all entries, so it's done after they're all built.
+fn build_list_items(
+    config: &Config,
+    align: bool,
+    items: Vec<(&Entry, String, Option<String>, Option<String>)>,
+) -> Vec<String> {
+    let style = |entry: &Entry, label: String| {
+        let label = with_ignored_style(config, entry, label);
+        let label = with_hyperlink(config, entry, label);
+
+        with_git_status(config, entry, label)
+    };
+
+    let align = align && items.iter().any(|(_, _, size, _)| size.is_some());
+
+    if !align {
+        return items
+            .into_iter()
+            .map(|(entry, label, size, details)| {
+                let item = [Some(label), size, details]
+                    .into_iter()
+                    .flatten()
+                    .collect::<Vec<_>>()
+                    .join(" ");
+
+                style(entry, item)
+            })
+            .collect();
+    }
+
+    let items: Vec<_> = items
+        .into_iter()
+        .map(|(entry, label, size, details)| (style(entry, label), size, details))
+        .collect();
+
+    let label_width = items
+        .iter()
+        .map(|(label, ..)| visible_length(label))
+        .max()
+        .unwrap_or_default();
+    let unit_width = items
+        .iter()
+        .filter_map(|(_, size, _)| size.as_deref())
+        .map(get_unit_width)
+        .max()
+        .unwrap_or_default();
+    let items: Vec<_> = items
+        .into_iter()
+        .map(|(label, size, details)| {
+            let size = size.map(|size| {
+                let padding = " ".repeat(unit_width - get_unit_width(&size));
+                format!("{size}{padding}")
+            });
+
+            (label, size, details)
+        })
+        .collect();
+    let size_width = items
+        .iter()
+        .filter_map(|(_, size, _)| size.as_deref())
+        .map(visible_length)
+        .max()
+        .unwrap_or_default();
+
+    items
+        .into_iter()
+        .map(|(label, size, details)| {
+            let size = size.unwrap_or_default();
+            let label_padding = " ".repeat(label_width - visible_length(&label));
+            let size_padding = " ".repeat(size_width - visible_length(&size));
+            let item = format!("{label}{label_padding} {size_padding}{size}");
+
+            match details {
+                Some(details) => format!("{item} {details}").trim_end().to_string(),
+                None => item.trim_end().to_string(),
+            }
+        })
+        .collect()
+}
+
+/// The width of a size's unit, like 2 for `1.5KB`. Colors are ignored, and
+/// sizes in bytes (e.g. with `--bytes`) have no unit.
+fn get_unit_width(size: &str) -> usize {
+    let size = ANSI_ESCAPE.replace_all(size, "");
+
+    size.len()
+        - size
+            .trim_end_matches(|char: char| !char.is_ascii_digit())
+            .len()
+}
+
+fn compare_entries(a: &Entry, b: &Entry, sort_by: SortBy, time_field: TimeField) -> Ordering {
+    let name = |entry: &Entry| {
+        entry
+            .path
+            .file_name()
+            .map(|name| name.to_os_string().to_ascii_lowercase())
+    };
+
+    // Directories only have a size with `--du`, as their own size (e.g. 4096
+    // bytes) is not meaningful.
+    let size = |entry: &Entry| get_entry_size(entry).unwrap_or_default();
+
+    let time = |entry: &Entry| {
+        entry
+            .metadata
+            .as_ref()
+            .and_then(|metadata| get_time(metadata, time_field))
+            .unwrap_or(SystemTime::UNIX_EPOCH)
+    };
+
+    let extension = |entry: &Entry| {
+        entry
+            .path
+            .extension()
+            .map(|ext| ext.to_os_string().to_ascii_lowercase())
+    };
+
+    let ordering = match sort_by {
+        SortBy::Name => Ordering::Equal,
+        SortBy::Size => size(b).cmp(&size(a)),
+        SortBy::Time => time(b).cmp(&time(a)),
+        SortBy::Extension => extension(a).cmp(&extension(b)),
+        SortBy::Version => {
+            let name = |entry: &Entry| {
+                entry
+                    .path
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .to_lowercase()
+            };
+
+            compare_natural(&name(a), &name(b))
+        }
+    };
+
+    // Entries with the same sorting key are always sorted by name.
+    ordering.then_with(|| name(a).cmp(&name(b)))
+}
+
+/// Compare strings so that sequences of digits are compared by their numeric
+/// value, rather than character by character.
+fn compare_natural(a: &str, b: &str) -> Ordering {
+    let mut a = a.chars().peekable();
+    let mut b = b.chars().peekable();
+
+    let take_number = |chars: &mut std::iter::Peekable<std::str::Chars>| {
+        let mut number = String::new();
+
+        while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+            number.push(digit);
+        }
+
+        number
+    };
+
+    loop {
+        match (a.peek().copied(), b.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let x = take_number(&mut a);
+                let y = take_number(&mut b);
+                let x_value = x.trim_start_matches('0');
+                let y_value = y.trim_start_matches('0');
+
+                // Numbers with more digits are bigger; same length numbers can
+                // be compared as strings. Leading zeros are used as a
+                // tie-breaker.
+                let ordering = x_value
+                    .len()
+                    .cmp(&y_value.len())
+                    .then_with(|| x_value.cmp(y_value))
+                    .then_with(|| x.len().cmp(&y.len()));
+
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            (Some(x), Some(y)) => {
+                if x != y {
+                    return x.cmp(&y);
+                }
+
+                a.next();
+                b.next();
+            }
+        }
+    }
+}
+
+/// Serialize the configuration back to TOML. Color rules are stored as regular
+/// colors, so they're moved back to their own table, keeping their order.
+fn format_config(config: &Config) -> Result<String, Error> {
+    let mut table = toml::Table::try_from(config)?;
+    let mut rules = String::new();
+
+    if let Some(toml::Value::Table(colors)) = table.get_mut("colors") {
+        for (_, key) in &config.color_rules {
+            if let Some(color) = colors.remove(key) {
+                rules.push_str(&format!("{} = {color}\n", toml::Value::from(key.as_str())));
+            }
+        }
+    }
+
+    let mut output = toml::to_string(&table)?;
+
+    if !rules.is_empty() {
+        output.push_str(&format!("\n[color_rules]\n{rules}"));
+    }
+
+    Ok(output)
+}
+
+fn get_config_file() -> Result<PathBuf, Error> {
+    let config_dir = if let Ok(config_home) = std::env::var("XDG_CONFIG_HOME") {
+        PathBuf::from(&config_home)
+    } else {
+        dirs::home_dir()
+            .ok_or(Error::HomeDirNotFound)?
+            .join(".config")
+    };
+
+    Ok(config_dir.join("ll.toml"))
+}
+
+fn get_config(cmd: &Cmd) -> Result<Config, Error> {
+    let toml_str = include_str!("config.toml");
+    let mut config: Config = toml::from_str(toml_str).expect("Failed to parse TOML file");
+    let config_file = match &cmd.config {
+        Some(path) if !path.is_file() => return Err(Error::ConfigNotFound(path.clone())),
+        Some(path) => path.clone(),
+        None => get_config_file()?,
+    };
+
+    let mut custom_configs = vec![read_config_file(&config_file)?];
+
+    if let Some(project_file) = find_project_config_file(cmd) {
+        custom_configs.push(read_config_file(&project_file)?);
+    }
+
+    custom_configs.push(read_env_config()?);
+
+    // Themes are applied on top of the default colors, but user-defined colors
+    // always win.
+    let theme = cmd.theme.clone().or_else(|| {
+        custom_configs
+            .iter()
+            .rev()
+            .find_map(|custom_config| custom_config.theme.clone())
+    });
+
+    if let Some(theme) = theme {
+        config.colors.extend(get_theme_colors(&theme)?);
+    }
+
+    for custom_config in custom_configs {
+        warn_invalid_values(&config, &custom_config);
+        merge_config(&mut config, custom_config)?;
+    }
+
+    if cmd.ls_colors || config.ls_colors {
+        apply_ls_colors(&mut config);
+    }
+
+    Ok(config)
+}
+
+fn read_config_file(path: &Path) -> Result<OptionalConfig, Error> {
+    if !path.exists() {
+        return Ok(OptionalConfig::default());
+    }
+
+    let toml_str = fs::read_to_string(path)?;
+    let deserializer = toml::Deserializer::new(&toml_str);
+    let mut unknown_keys = vec![];
+
+    let mut custom_config: OptionalConfig =
+        serde_ignored::deserialize(deserializer, |key| unknown_keys.push(key.to_string()))
+            .map_err(|error| Error::Config(path.to_path_buf(), Box::new(error)))?;
+
+    for key in unknown_keys {
+        eprintln!("WARNING: {}: unknown key {key:?}", path.display());
+    }
+
+    custom_config.path = Some(path.to_path_buf());
+
+    Ok(custom_config)
+}
+
+/// Warn about values that would be silently ignored, like invalid color names
+/// or icons referencing aliases that don't exist.
+fn warn_invalid_values(config: &Config, custom_config: &OptionalConfig) {
+    let Some(path) = &custom_config.path else {
+        return;
+    };
+
+    let warn = |key: String, message: String| {
+        eprintln!("WARNING: {}: {message} for {key:?}", path.display());
+    };
+
+    let colors = custom_config
+        .colors
+        .iter()
+        .flatten()
+        .map(|(name, color)| (format!("colors.{name}"), color));
+    let color_rules = custom_config
+        .color_rules
+        .iter()
+        .flatten()
+        .map(|(pattern, color)| (format!("color_rules.{pattern}"), color));
+
+    for (key, color) in colors.chain(color_rules) {
+        if let Some(color) = color.style().color {
+            if parse_color(&color).is_none() {
+                warn(key, format!("invalid color {color:?}"));
+            }
+        }
+    }
+
+    // Icons are single glyphs, so words are most likely aliases.
+    let is_alias = |icon: &str| {
+        icon.chars().count() > 1 && icon.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    };
+    let alias_exists = |icon: &str| {
+        config.aliases.contains_key(icon)
+            || custom_config
+                .aliases
+                .as_ref()
+                .is_some_and(|aliases| aliases.contains_key(icon))
+    };
+
+    for (table, icons) in [
+        ("files", &custom_config.files),
+        ("folders", &custom_config.folders),
+        ("kinds", &custom_config.kinds),
+        ("content_types", &custom_config.content_types),
+    ] {
+        for (name, icon) in icons.iter().flatten() {
+            if is_alias(icon) && !alias_exists(icon) {
+                warn(
+                    format!("{table}.{name}"),
+                    format!("unknown icon alias {icon:?}"),
+                );
+            }
+        }
+    }
+}
+
+/// Find the project's configuration file (`.ll.toml`), by walking up from the
+/// listed directory.
+fn find_project_config_file(cmd: &Cmd) -> Option<PathBuf> {
+    resolve_basedir(&resolve_input(cmd))?
+        .ancestors()
+        .map(|dir| dir.join(".ll.toml"))
+        .find(|path| path.is_file())
+}
+
+/// Read configuration overrides from `LL_*` environment variables (e.g.
+/// `LL_THEME=nord` or `LL_ICONS=off`). Lists are comma-separated.
+fn read_env_config() -> Result<OptionalConfig, Error> {
+    let var = |name: &str| std::env::var(name).ok().filter(|value| !value.is_empty());
+
+    let flag = |name: &str| -> Result<Option<bool>, Error> {
+        let Some(value) = var(name) else {
+            return Ok(None);
+        };
+
+        match value.to_lowercase().as_str() {
+            "1" | "true" | "yes" | "on" => Ok(Some(true)),
+            "0" | "false" | "no" | "off" => Ok(Some(false)),
+            _ => Err(Error::Env(name.to_string(), value)),
+        }
+    };
+
+    fn parse<T: std::str::FromStr>(name: &str) -> Result<Option<T>, Error> {
+        let Some(value) = std::env::var(name).ok().filter(|value| !value.is_empty()) else {
+            return Ok(None);
+        };
+
+        match value.parse() {
+            Ok(value) => Ok(Some(value)),
+            Err(_) => Err(Error::Env(name.to_string(), value)),
+        }
+    }
+
+    let list = |name: &str| {
+        var(name).map(|value| {
+            value
+                .split(',')
+                .map(|item| item.trim().to_string())
+                .filter(|item| !item.is_empty())
+                .collect::<Vec<String>>()
+        })
+    };
+
+    let mut ignore = HashMap::new();
+
+    if let Some(files) = list("LL_IGNORE_FILES") {
+        ignore.insert("files".to_string(), files);
+    }
+
+    if let Some(folders) = list("LL_IGNORE_FOLDERS") {
+        ignore.insert("folders".to_string(), folders);
+    }
+
+    Ok(OptionalConfig {
+        theme: var("LL_THEME"),
+        ls_colors: flag("LL_LS_COLORS")?,
+        icons: flag("LL_ICONS")?,
+        hyperlink: flag("LL_HYPERLINK")?,
+        pager: flag("LL_PAGER")?,
+        summary: flag("LL_SUMMARY")?,
+        sizes: flag("LL_SIZES")?,
+        binary_sizes: flag("LL_BINARY_SIZES")?,
+        highlight_world_writable: flag("LL_HIGHLIGHT_WORLD_WRITABLE")?,
+        detect_binary_files: flag("LL_DETECT_BINARY_FILES")?,
+        git_ignore: flag("LL_GIT_IGNORE")?,
+        column_gap: parse("LL_COLUMN_GAP")?,
+        padding: parse("LL_PADDING")?,
+        time_format: var("LL_TIME_FORMAT"),
+        thousands_separator: var("LL_THOUSANDS_SEPARATOR"),
+        ignore: Some(ignore),
+        ..OptionalConfig::default()
+    })
+}
+
+/// Merge a configuration file on top of the current configuration.
+fn merge_config(config: &mut Config, custom_config: OptionalConfig) -> Result<(), Error> {
+    if let Some(icons) = custom_config.icons {
+        config.icons = icons;
+    }
+
+    if let Some(hyperlink) = custom_config.hyperlink {
+        config.hyperlink = hyperlink;
+    }
+
+    if let Some(pager) = custom_config.pager {
+        config.pager = pager;
+    }
+
+    if let Some(summary) = custom_config.summary {
+        config.summary = summary;
+    }
+
+    if let Some(sizes) = custom_config.sizes {
+        config.sizes = sizes;
+    }
+
+    if let Some(binary_sizes) = custom_config.binary_sizes {
+        config.binary_sizes = binary_sizes;
+    }
+
+    if let Some(highlight_world_writable) = custom_config.highlight_world_writable {
+        config.highlight_world_writable = highlight_world_writable;
+    }
+
+    if let Some(detect_binary_files) = custom_config.detect_binary_files {
+        config.detect_binary_files = detect_binary_files;
+    }
+
+    if let Some(git_ignore) = custom_config.git_ignore {
+        config.git_ignore = git_ignore;
+    }
+
+    if let Some(column_gap) = custom_config.column_gap {
+        config.column_gap = column_gap;
+    }
+
+    if let Some(padding) = custom_config.padding {
+        config.padding = padding;
+    }
+
+    if let Some(ls_colors) = custom_config.ls_colors {
+        config.ls_colors = ls_colors;
+    }
+
+    if custom_config.time_format.is_some() {
+        config.time_format = custom_config.time_format;
+    }
+
+    if custom_config.thousands_separator.is_some() {
+        config.thousands_separator = custom_config.thousands_separator;
+    }
+
+    config
+        .folders
+        .extend(custom_config.folders.unwrap_or_default());
+
+    config.files.extend(custom_config.files.unwrap_or_default());
+
+    config.kinds.extend(custom_config.kinds.unwrap_or_default());
+
+    config
+        .content_types
+        .extend(custom_config.content_types.unwrap_or_default());
+
+    config
+        .colors
+        .extend(custom_config.colors.unwrap_or_default());
+
+    // Color rules are stored as regular colors, keyed by their pattern. Rules
+    // from the last merged file are matched first.
+    let mut color_rules = vec![];
+
+    for (pattern, color) in custom_config.color_rules.unwrap_or_default() {
+        color_rules.push((glob::Pattern::new(&pattern)?, pattern.clone()));
+        config.colors.insert(pattern, color);
+    }
+
+    config.color_rules.splice(0..0, color_rules);
+
+    config
+        .aliases
+        .extend(custom_config.aliases.unwrap_or_default());
+
+    let ignore = custom_config.ignore.unwrap_or_default();
+
+    if let Some(files) = ignore.get("files") {
+        config.ignore.insert("files".to_string(), files.clone());
+    }
+
+    if let Some(folders) = ignore.get("folders") {
+        config.ignore.insert("folders".to_string(), folders.clone());
+    }
+
+    Ok(())
+}
+
+/// Override colors with the ones defined by the `LS_COLORS` environment
+/// variable, if it's set.
+fn apply_ls_colors(config: &mut Config) {
+    let Ok(value) = std::env::var("LS_COLORS") else {
+        return;
+    };
+
+    for (key, style) in ls_colors::parse(&value) {
+        let color_types: &[&str] = match key.as_str() {
+            "di" => &["dir", "hidden_dir"],
+            "fi" => &["file", "hidden"],
+            "ex" => &["executable_file"],
+            "su" | "sg" => &["warning"],
+            "ln" => &["link"],
+            "or" => &["dead_link"],
+            _ if key.starts_with('*') => {
+                if let Ok(pattern) = glob::Pattern::new(&key) {
+                    config.color_rules.push((pattern, key.clone()));
+                    config.colors.insert(key, ColorValue::Style(style));
+                }
+
+                continue;
+            }
+            _ => &[],
+        };
+
+        for color_type in color_types {
+            config
+                .colors
+                .insert((*color_type).to_string(), ColorValue::Style(style.clone()));
+        }
+    }
+}
+
+fn get_theme_colors(theme: &str) -> Result<HashMap<String, ColorValue>, Error> {
+    let toml_str = match theme {
+        "dracula" => include_str!("themes/dracula.toml"),
+        "gruvbox" => include_str!("themes/gruvbox.toml"),
+        "nord" => include_str!("themes/nord.toml"),
+        "solarized-light" => include_str!("themes/solarized-light.toml"),
+        _ => return Err(Error::ThemeNotFound(theme.to_string())),
+    };
+
+    Ok(toml::from_str(toml_str).expect("Failed to parse TOML file"))
+}
+
+fn display_in_columns(
+    out: &mut impl Write,
+    cmd: &Cmd,
+    config: &Config,
+    list: &[String],
+) -> io::Result<()> {
+    let max_item_len = list
+        .iter()
+        .map(|i| visible_length(i))
+        .max()
+        .unwrap_or_default();
+
+    let term_width = get_terminal_width(cmd).unwrap_or(1);
+
+    let list_len = list.len();
+    let indent = 2;
+    let col_gap = config.column_gap;
+    let col_width = indent + max_item_len + col_gap;
+    let mut cols = max(1, term_width / col_width);
+
+    if let Some(max_cols) = cmd.columns {
+        cols = cols.min(max_cols.max(1));
+    }
+
+    let mut rows = max(1, list_len.div_ceil(cols));
+
+    if rows == 1 {
+        cols = 1;
+        rows = list_len;
+    }
+
+    for row in 0..rows {
+        for col in 0..cols {
+            // Columns are filled top to bottom, unless `-x` is used.
+            let index = if cmd.across {
+                row * cols + col
+            } else {
+                col * rows + row
+            };
+
+            if index < list_len {
+                let value = &list[index];
+                let value_len = visible_length(value);
+                let padding = " ".repeat(col_width - indent - value_len);
+
+                write!(out, "{}{value}{padding}", " ".repeat(indent))?;
+            }
+        }
+
+        writeln!(out)?;
+    }
+
+    Ok(())
+}
+
+/// Display entries separated by commas, like `ls -m`. Lines are wrapped
+/// between entries, so names are never broken.
+fn display_with_commas(out: &mut impl Write, cmd: &Cmd, list: &[String]) -> io::Result<()> {
+    let indent = 2;
+    let term_width = get_terminal_width(cmd).unwrap_or(80);
+    let mut line_len = 0;
+
+    for (index, item) in list.iter().enumerate() {
+        let separator = if index + 1 < list.len() { "," } else { "" };
+        let item_len = visible_length(item) + separator.len();
+
+        if line_len > 0 && line_len + 1 + item_len > term_width {
+            writeln!(out)?;
+            line_len = 0;
+        }
+
+        if line_len == 0 {
+            write!(out, "{}", " ".repeat(indent))?;
+            line_len = indent;
+        } else {
+            write!(out, " ")?;
+            line_len += 1;
+        }
+
+        write!(out, "{item}{separator}")?;
+        line_len += item_len;
+    }
+
+    if line_len > 0 {
+        writeln!(out)?;
+    }
+
+    Ok(())
+}
+
+fn display_in_table(
+    out: &mut impl Write,
+    config: &Config,
+    rows: &[Vec<String>],
+    right_aligned: &[usize],
+) -> io::Result<()> {
+    let cols = rows.iter().map(Vec::len).max().unwrap_or_default();
+    let widths: Vec<usize> = (0..cols)
+        .map(|col| {
+            rows.iter()
+                .filter_map(|row| row.get(col))
+                .map(|cell| visible_length(cell))
+                .max()
+                .unwrap_or_default()
+        })
+        .collect();
+
+    for row in rows {
+        let last = row.len().saturating_sub(1);
+
+        let line: Vec<String> = row
+            .iter()
+            .enumerate()
+            .map(|(col, cell)| {
+                let padding = config
+                    .padding
+                    .to_string()
+                    .repeat(widths[col] - visible_length(cell));
+
+                if right_aligned.contains(&col) {
+                    format!("{padding}{cell}")
+                } else if col == last {
+                    cell.clone()
+                } else {
+                    format!("{cell}{padding}")
+                }
+            })
+            .collect();
+
+        writeln!(out, "{}", line.join(" "))?;
+    }
+
+    Ok(())
+}
+
+/// The width used to lay out entries: `--width`, then the `COLUMNS` variable,
+/// and finally the terminal's actual size.
+fn get_terminal_width(cmd: &Cmd) -> Option<usize> {
+    let columns = std::env::var("COLUMNS")
+        .ok()
+        .and_then(|columns| columns.trim().parse().ok());
+
+    cmd.width
+        .or(columns)
+        .filter(|width| *width > 0)
+        .or_else(|| terminal::size().ok().map(|(width, _)| width.into()))
+}
+
+/// Shorten entries that are wider than the terminal, so they don't wrap and
+/// break the layout. Nothing is truncated when the output is not a terminal.
+fn truncate_items(cmd: &Cmd, list: Vec<String>) -> Vec<String> {
+    let width = match get_terminal_width(cmd) {
+        Some(width) if io::stdout().is_terminal() && !cmd.no_truncate => width,
+        _ => return list,
+    };
+
+    // Entries are indented, and followed by the gap between columns.
+    let max_width = width.saturating_sub(4).max(1);
+
+    list.into_iter()
+        .map(|item| truncate_middle(&item, max_width))
+        .collect()
+}
+
+/// Truncate the middle of a label with `…`, as both the beginning of a name and
+/// its extension tend to be meaningful. Colors and hyperlinks are kept.
+fn truncate_middle(input: &str, max_width: usize) -> String {
+    let length = visible_length(input);
+
+    if length <= max_width {
+        return input.to_string();
+    }
+
+    // Escape sequences take no space, except for thumbnails.
+    let mut tokens: Vec<(&str, usize)> = vec![];
+    let mut last = 0;
+
+    for found in ESCAPE_SEQUENCE.find_iter(input) {
+        tokens.extend(
+            input[last..found.start()]
+                .char_indices()
+                .map(|(index, char)| {
+                    let start = last + index;
+                    (&input[start..start + char.len_utf8()], 1)
+                }),
+        );
+
+        let width = if found.as_str().starts_with("\x1b[") || found.as_str().starts_with("\x1b]8") {
+            0
+        } else {
+            thumbnails::WIDTH
+        };
+
+        tokens.push((found.as_str(), width));
+        last = found.end();
+    }
+
+    tokens.extend(input[last..].char_indices().map(|(index, char)| {
+        let start = last + index;
+        (&input[start..start + char.len_utf8()], 1)
+    }));
+
+    let available = max_width.saturating_sub(1);
+    let head = available.div_ceil(2);
+    let tail = length - available / 2;
+
+    let mut output = String::new();
+    let mut position = 0;
+    let mut ellipsis = false;
+
+    for (token, width) in tokens {
+        let keep = width == 0 || position + width <= head || position >= tail;
+
+        if keep {
+            output.push_str(token);
+        } else if !ellipsis {
+            output.push('…');
+            ellipsis = true;
+        }
+
+        position += width;
+    }
+
+    output
+}
+
+fn visible_length(input: &str) -> usize {
+    // Thumbnails take a fixed number of cells, regardless of their size.
+    let input = THUMBNAIL.replace_all(input, " ".repeat(thumbnails::WIDTH));
+
+    // Strip both SGR (colors) and OSC 8 (hyperlinks) sequences.
+    let stripped = ANSI_ESCAPE.replace_all(&input, "");
+
+    stripped.chars().count()
+}
+
+/// The character appended to names with `--classify`. Directories already
+/// end with `/`, and symlinks are handled separately, as their metadata is
+/// their target's.
+#[cfg(unix)]
+fn get_classifier(path: &Path, metadata: &Metadata) -> Option<char> {
+    let file_type = metadata.file_type();
+
+    if file_type.is_fifo() {
+        Some('|')
+    } else if file_type.is_socket() {
+        Some('=')
+    } else if is_executable(path, metadata) {
+        Some('*')
+    } else {
+        None
+    }
+}
+
+#[cfg(windows)]
+fn get_classifier(path: &Path, metadata: &Metadata) -> Option<char> {
+    is_executable(path, metadata).then_some('*')
+}
+
+#[cfg(unix)]
+fn is_executable(_path: &Path, metadata: &Metadata) -> bool {
+    metadata.permissions().mode() & 0o111 != 0
+}
+
+/// Whether a file is an executable that runs with the privileges of its owner
+/// or group (setuid or setgid).
+#[cfg(unix)]
+fn is_setuid(metadata: &Metadata) -> bool {
+    let mode = metadata.permissions().mode();
+
+    metadata.is_file() && mode & 0o111 != 0 && mode & 0o6000 != 0
+}
+
+#[cfg(windows)]
+fn is_setuid(_metadata: &Metadata) -> bool {
+    false
+}
+
+/// The color of a world-writable entry's permissions. Directories without the
+/// sticky bit use the warning color, as anyone can delete or replace the files
+/// in them.
+#[cfg(unix)]
+fn get_world_writable_color_type(metadata: &Metadata) -> Option<&'static str> {
+    let mode = metadata.permissions().mode();
+
+    if mode & 0o002 == 0 {
+        None
+    } else if metadata.is_dir() && mode & 0o1000 == 0 {
+        Some("warning")
+    } else {
+        Some("world_writable")
+    }
+}
+
+#[cfg(windows)]
+fn get_world_writable_color_type(_metadata: &Metadata) -> Option<&'static str> {
+    None
+}
+
+#[cfg(windows)]
+fn is_executable(path: &Path, _metadata: &Metadata) -> bool {
+    if let Some(ext) = path.extension() {
+        matches!(ext.to_str(), Some("exe" | "bat" | "cmd"))
     } else {
         false
     }
 }
 
+#[cfg(unix)]
+fn format_permissions(metadata: &Metadata) -> String {
+    let file_type = metadata.file_type();
+    let kind = if file_type.is_dir() {
+        'd'
+    } else if file_type.is_symlink() {
+        'l'
+    } else if file_type.is_fifo() {
+        'p'
+    } else if file_type.is_socket() {
+        's'
+    } else if file_type.is_char_device() {
+        'c'
+    } else if file_type.is_block_device() {
+        'b'
+    } else {
+        '-'
+    };
+
+    format_mode(kind, metadata.permissions().mode())
+}
+
+/// Format a file type and unix permission bits like `drwxr-xr-x`. The setuid,
+/// setgid, and sticky bits replace the execute bit they're paired with, using
+/// an uppercase letter when it isn't set (e.g. `-rwsr-xr-x` or `drwxrwxrwT`).
+fn format_mode(kind: char, mode: u32) -> String {
+    let mut output = kind.to_string();
+
+    for (shift, special, letter) in [(6, 0o4000, 's'), (3, 0o2000, 's'), (0, 0o1000, 't')] {
+        let bits = (mode >> shift) & 0o7;
+
+        output.push(if bits & 0o4 == 0 { '-' } else { 'r' });
+        output.push(if bits & 0o2 == 0 { '-' } else { 'w' });
+        output.push(match (mode & special != 0, bits & 0o1 != 0) {
+            (true, true) => letter,
+            (true, false) => letter.to_ascii_uppercase(),
+            (false, true) => 'x',
+            (false, false) => '-',
+        });
+    }
+
+    output
+}
+
+#[cfg(unix)]
+fn format_octal_permissions(metadata: &Metadata) -> String {
+    format!("{:04o}", metadata.permissions().mode() & 0o7777)
+}
+
+#[cfg(windows)]
+fn format_octal_permissions(metadata: &Metadata) -> String {
+    let mode = if metadata.permissions().readonly() {
+        "0444"
+    } else {
+        "0666"
+    };
+
+    mode.to_string()
+}
+
+#[cfg(windows)]
+fn format_permissions(metadata: &Metadata) -> String {
+    let kind = if metadata.is_dir() {
+        'd'
+    } else if metadata.file_type().is_symlink() {
+        'l'
+    } else {
+        '-'
+    };
+
+    let mode = if metadata.permissions().readonly() {
+        "r--r--r--"
+    } else {
+        "rw-rw-rw-"
+    };
+
+    format!("{kind}{mode}")
+}
+
+#[cfg(unix)]
+fn get_owner(cmd: &Cmd, _path: &Path, metadata: &Metadata) -> String {
+    let uid = metadata.uid();
+
+    if cmd.numeric {
+        return uid.to_string();
+    }
+
+    users::user_name(uid).unwrap_or(uid.to_string())
+}
+
+/// The user's home directory, resolved once rather than for every entry.
+#[cfg(unix)]
+static HOME_DIR: LazyLock<Option<PathBuf>> = LazyLock::new(dirs::home_dir);
+
+/// The color of an entry's owner. Entries owned by someone else are dimmed,
+/// except for root-owned entries in the user's home directory, which are
+/// highlighted (e.g. files left behind by `sudo`).
+#[cfg(unix)]
+fn get_owner_color_type(path: &Path, metadata: &Metadata) -> &'static str {
+    let uid = metadata.uid();
+
+    if uid == users::current_user_id() {
+        return "owner";
+    }
+
+    let in_home_dir = || {
+        HOME_DIR.as_ref().is_some_and(|home_dir| {
+            std::path::absolute(path).is_ok_and(|path| path.starts_with(home_dir))
+        })
+    };
+
+    if uid == 0 && in_home_dir() {
+        "root_owned"
+    } else {
+        "not_mine"
+    }
+}
+
+#[cfg(windows)]
+fn get_owner_color_type(_path: &Path, _metadata: &Metadata) -> &'static str {
+    "owner"
+}
+
+#[cfg(windows)]
+fn get_owner(_cmd: &Cmd, path: &Path, _metadata: &Metadata) -> String {
+    users::owner(path).unwrap_or("-".to_string())
+}
+
+#[cfg(unix)]
+fn get_group(cmd: &Cmd, metadata: &Metadata) -> String {
+    let gid = metadata.gid();
+
+    if cmd.numeric {
+        return gid.to_string();
+    }
+
+    users::group_name(gid).unwrap_or(gid.to_string())
+}
+
+#[cfg(windows)]
+fn get_group(_cmd: &Cmd, _metadata: &Metadata) -> String {
+    "-".to_string()
+}
+
+fn format_inode(path: &Path) -> String {
+    get_inode(path).map_or("?".to_string(), |inode| inode.to_string())
+}
+
+// Like `ls`, symlinks have their own inode displayed, rather than their
+// target's.
+#[cfg(unix)]
+fn get_inode(path: &Path) -> Option<u64> {
+    fs::symlink_metadata(path)
+        .ok()
+        .map(|metadata| metadata.ino())
+}
+
+#[cfg(windows)]
+fn get_inode(path: &Path) -> Option<u64> {
+    use std::os::windows::{fs::OpenOptionsExt, io::AsRawHandle};
+    use windows_sys::Win32::Storage::FileSystem::{
+        GetFileInformationByHandle, BY_HANDLE_FILE_INFORMATION, FILE_FLAG_BACKUP_SEMANTICS,
+        FILE_FLAG_OPEN_REPARSE_POINT,
+    };
+
+    // Directories can only be opened with backup semantics.
+    let file = fs::OpenOptions::new()
+        .read(true)
+        .custom_flags(FILE_FLAG_BACKUP_SEMANTICS | FILE_FLAG_OPEN_REPARSE_POINT)
+        .open(path)
+        .ok()?;
+
+    // SAFETY: the handle is valid for as long as `file` is alive, and `info`
+    // is only read when the call succeeds.
+    unsafe {
+        let mut info: BY_HANDLE_FILE_INFORMATION = std::mem::zeroed();
+
+        if GetFileInformationByHandle(file.as_raw_handle(), &mut info) == 0 {
+            return None;
+        }
+
+        Some((u64::from(info.nFileIndexHigh) << 32) | u64::from(info.nFileIndexLow))
+    }
+}
+
 fn get_file_size(metadata: &Metadata) -> u64 {
     #[cfg(unix)]
     {
@@ -464,7 +4728,186 @@ fn get_file_size(metadata: &Metadata) -> u64 {
     }
 }
 
-fn ignore_entry(entry: &Entry, folders: &[String], files: &[String]) -> bool {
+/// The space allocated for the file on disk, which is smaller than its size for
+/// sparse and compressed files. Only available on Unix.
+fn get_allocated_size(metadata: &Metadata) -> Option<u64> {
+    #[cfg(unix)]
+    {
+        // Blocks are always counted in units of 512 bytes, regardless of the
+        // filesystem's block size.
+        Some(metadata.blocks() * 512)
+    }
+
+    #[cfg(windows)]
+    {
+        let _ = metadata;
+        None
+    }
+}
+
+/// The SELinux security context of an entry (e.g.
+/// `unconfined_u:object_r:user_home_t:s0`), which is stored as an extended
+/// attribute. Systems without SELinux have none.
+fn get_security_context(path: &Path) -> Option<String> {
+    let context = xattr::get(path, "security.selinux")?;
+    let context = context.strip_suffix(b"\0").unwrap_or(&context);
+
+    Some(String::from_utf8_lossy(context).to_string())
+}
+
+/// Describe the space allocated for a file on disk, like `(2.1GB on disk)`,
+/// when `--on-disk` is used.
+fn format_disk_size(config: &Config, metadata: &Metadata) -> Option<String> {
+    let allocated =
+        get_allocated_size(metadata).filter(|_| config.on_disk && metadata.is_file())?;
+
+    Some(format_with_color(
+        config,
+        format!("({} on disk)", format_size(config, allocated)),
+        "disk_size",
+    ))
+}
+
+/// Whether the file takes much less space on disk than its size, like VM
+/// images and databases with unwritten regions. Small files are ignored, as
+/// some filesystems store them with no blocks allocated.
+fn is_sparse(metadata: &Metadata) -> bool {
+    let size = get_file_size(metadata);
+
+    metadata.is_file()
+        && size >= 1024 * 1024
+        && get_allocated_size(metadata).is_some_and(|allocated| allocated < size / 2)
+}
+
+/// Apply the filters given on the command line (e.g. `--only` or `--type`).
+/// Directories are always kept with `--tree` and `-R`, so matching entries
+/// inside them can be listed.
+fn filter_entry(cmd: &Cmd, entry: &Entry) -> bool {
+    matches_only(cmd, entry)
+        && matches_type(cmd, entry)
+        && matches_ext(cmd, entry)
+        && matches_regex(cmd, entry)
+        && matches_size(cmd, entry)
+        && matches_time(cmd, entry)
+        && matches_owner(cmd, entry)
+        && (!cmd.broken_links || (entry.link_target.is_some() && entry.metadata.is_none()))
+}
+
+fn matches_only(cmd: &Cmd, entry: &Entry) -> bool {
+    if cmd.only.is_empty() {
+        return true;
+    }
+
+    let basename = entry.path.file_name().unwrap_or_default().to_string_lossy();
+    let options = glob::MatchOptions {
+        case_sensitive: false,
+        ..glob::MatchOptions::new()
+    };
+
+    cmd.only
+        .iter()
+        .any(|pattern| pattern.matches_with(&basename, options))
+}
+
+fn matches_type(cmd: &Cmd, entry: &Entry) -> bool {
+    if cmd.r#type.is_empty() {
+        return true;
+    }
+
+    let entry_type = match &entry.metadata {
+        _ if entry.link_target.is_some() => EntryType::Symlink,
+        Some(metadata) if metadata.is_dir() => EntryType::Directory,
+        Some(metadata) if is_executable(&entry.path, metadata) => EntryType::Executable,
+        _ => EntryType::File,
+    };
+
+    cmd.r#type.iter().any(|filter| match filter {
+        // Executables are files too.
+        EntryType::File => matches!(entry_type, EntryType::File | EntryType::Executable),
+        _ => *filter == entry_type,
+    })
+}
+
+fn matches_ext(cmd: &Cmd, entry: &Entry) -> bool {
+    if cmd.ext.is_empty() {
+        return true;
+    }
+
+    let Some(extname) = entry.path.extension() else {
+        return false;
+    };
+    let extname = extname.to_string_lossy();
+
+    cmd.ext
+        .iter()
+        .any(|ext| ext.trim_start_matches('.').eq_ignore_ascii_case(&extname))
+}
+
+fn matches_regex(cmd: &Cmd, entry: &Entry) -> bool {
+    let Some(regex) = &cmd.r#match else {
+        return true;
+    };
+
+    regex.is_match(&entry.path.file_name().unwrap_or_default().to_string_lossy())
+}
+
+fn matches_size(cmd: &Cmd, entry: &Entry) -> bool {
+    let Some(filter) = &cmd.size else {
+        return true;
+    };
+
+    // Directory sizes don't reflect their contents, so they can't be compared.
+    match &entry.metadata {
+        Some(metadata) if !metadata.is_dir() => {
+            get_file_size(metadata).cmp(&filter.size) == filter.ordering
+        }
+        _ => false,
+    }
+}
+
+fn matches_time(cmd: &Cmd, entry: &Entry) -> bool {
+    if cmd.newer_than.is_none() && cmd.older_than.is_none() {
+        return true;
+    }
+
+    let Some(entry_time) = entry
+        .metadata
+        .as_ref()
+        .and_then(|metadata| get_time(metadata, cmd.time))
+    else {
+        return false;
+    };
+
+    cmd.newer_than.is_none_or(|time| entry_time > time)
+        && cmd.older_than.is_none_or(|time| entry_time < time)
+}
+
+#[cfg(unix)]
+fn matches_owner(cmd: &Cmd, entry: &Entry) -> bool {
+    if cmd.owner.is_none() && cmd.group.is_none() {
+        return true;
+    }
+
+    // Like the long listing format, symlinks are matched by their own owner.
+    let Ok(metadata) = fs::symlink_metadata(&entry.path) else {
+        return false;
+    };
+
+    cmd.owner.is_none_or(|uid| metadata.uid() == uid)
+        && cmd.group.is_none_or(|gid| metadata.gid() == gid)
+}
+
+#[cfg(windows)]
+fn matches_owner(_cmd: &Cmd, _entry: &Entry) -> bool {
+    true
+}
+
+fn ignore_entry(
+    entry: &Entry,
+    folders: &[String],
+    files: &[String],
+    globs: &[glob::Pattern],
+) -> bool {
     let basename = entry
         .path
         .file_name()
@@ -482,6 +4925,18 @@ fn ignore_entry(entry: &Entry, folders: &[String], files: &[String]) -> bool {
         .to_lowercase();
     let extname = format!(".{extname}");
 
+    let options = glob::MatchOptions {
+        case_sensitive: false,
+        ..glob::MatchOptions::new()
+    };
+
+    if globs
+        .iter()
+        .any(|pattern| pattern.matches_with(&basename, options))
+    {
+        return false;
+    }
+
     // If we're able to retrieve the metadata, be specific about the type of
     // entry and its ignored values; otherwise, compare the file name against
     // everything.
@@ -512,3 +4967,292 @@ fn expand_path(input: &str) -> String {
 
     path.to_string()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    /// The default configuration, without any user configuration.
+    fn default_config() -> Config {
+        toml::from_str(include_str!("config.toml")).unwrap()
+    }
+
+    #[test]
+    fn compares_numbers_in_names_by_their_value() {
+        assert_eq!(compare_natural("file2", "file10"), Ordering::Less);
+        assert_eq!(compare_natural("file10", "file9"), Ordering::Greater);
+        assert_eq!(compare_natural("v1.10.0", "v1.9.3"), Ordering::Greater);
+        assert_eq!(compare_natural("a", "b"), Ordering::Less);
+        assert_eq!(compare_natural("file", "file1"), Ordering::Less);
+        assert_eq!(compare_natural("file1", "file1"), Ordering::Equal);
+    }
+
+    #[test]
+    fn uses_leading_zeros_as_a_tie_breaker() {
+        assert_eq!(compare_natural("file01", "file1"), Ordering::Greater);
+        assert_eq!(compare_natural("file001", "file2"), Ordering::Less);
+    }
+
+    #[test]
+    fn formats_relative_times() {
+        assert_eq!(format_relative_time(Duration::seconds(0)), "just now");
+        assert_eq!(format_relative_time(Duration::seconds(1)), "1 second ago");
+        assert_eq!(format_relative_time(Duration::minutes(5)), "5 minutes ago");
+        assert_eq!(format_relative_time(Duration::hours(25)), "1 day ago");
+        assert_eq!(format_relative_time(Duration::days(800)), "2 years ago");
+        assert_eq!(format_relative_time(Duration::hours(-3)), "in 3 hours");
+    }
+
+    #[test]
+    fn parses_size_filters() {
+        let filter = |ordering, size| Ok(SizeFilter { ordering, size });
+
+        assert_eq!(
+            parse_size_filter("+10M"),
+            filter(Ordering::Greater, 10_000_000)
+        );
+        assert_eq!(parse_size_filter("-4KiB"), filter(Ordering::Less, 4096));
+        assert_eq!(parse_size_filter("512B"), filter(Ordering::Equal, 512));
+        assert_eq!(parse_size_filter("0"), filter(Ordering::Equal, 0));
+    }
+
+    #[test]
+    fn rejects_invalid_size_filters() {
+        assert!(parse_size_filter("").is_err());
+        assert!(parse_size_filter("+").is_err());
+        assert!(parse_size_filter("ten").is_err());
+        assert!(parse_size_filter("10Q").is_err());
+    }
+
+    #[test]
+    fn parses_dates_as_time_thresholds() {
+        let expected = NaiveDate::from_ymd_opt(2024, 1, 1)
+            .unwrap()
+            .and_time(NaiveTime::MIN)
+            .and_local_timezone(Local)
+            .earliest()
+            .map(SystemTime::from);
+
+        assert_eq!(parse_time_threshold("2024-01-01").ok(), expected);
+    }
+
+    #[test]
+    fn parses_durations_as_time_thresholds() {
+        let age = |value: &str| {
+            let time = parse_time_threshold(value).unwrap();
+            SystemTime::now().duration_since(time).unwrap().as_secs()
+        };
+
+        assert!((30..35).contains(&age("30s")));
+        assert!((7200..7205).contains(&age("2h")));
+        assert!((604_800..604_805).contains(&age("1w")));
+    }
+
+    #[test]
+    fn rejects_invalid_time_thresholds() {
+        assert!(parse_time_threshold("").is_err());
+        assert!(parse_time_threshold("2d4").is_err());
+        assert!(parse_time_threshold("10x").is_err());
+        assert!(parse_time_threshold("2024-13-01").is_err());
+        assert!(parse_time_threshold("99999999999999y").is_err());
+        assert!(parse_time_threshold("9999999999w").is_err());
+        assert!(parse_time_threshold("99999999999999999999d").is_err());
+    }
+
+    #[test]
+    fn formats_permission_bits() {
+        assert_eq!(format_mode('-', 0o644), "-rw-r--r--");
+        assert_eq!(format_mode('d', 0o755), "drwxr-xr-x");
+        assert_eq!(format_mode('l', 0o777), "lrwxrwxrwx");
+        assert_eq!(format_mode('-', 0), "----------");
+    }
+
+    #[test]
+    fn formats_counts_with_thousands_separators() {
+        let mut config = default_config();
+        config.thousands_separator = Some(",".to_string());
+
+        assert_eq!(format_count(&config, 0), "0");
+        assert_eq!(format_count(&config, 999), "999");
+        assert_eq!(format_count(&config, 3410), "3,410");
+        assert_eq!(format_count(&config, 1_234_567), "1,234,567");
+        assert_eq!(
+            format_count(&config, u64::MAX),
+            "18,446,744,073,709,551,615"
+        );
+
+        config.thousands_separator = Some(String::new());
+        assert_eq!(format_count(&config, 1_234_567), "1234567");
+    }
+
+    #[test]
+    fn leaves_safe_names_unquoted() {
+        assert_eq!(quote_name("main.rs"), "main.rs");
+        assert_eq!(
+            quote_name("v1.2.3+build_4@host:5%"),
+            "v1.2.3+build_4@host:5%"
+        );
+        assert_eq!(quote_name("café"), "café");
+    }
+
+    #[test]
+    fn quotes_names_with_spaces_and_quotes() {
+        assert_eq!(quote_name("my file"), "'my file'");
+        assert_eq!(quote_name("it's"), "'it'\\''s'");
+        assert_eq!(quote_name("a*b"), "'a*b'");
+        assert_eq!(quote_name(""), "''");
+    }
+
+    #[test]
+    fn escapes_control_characters() {
+        assert_eq!(quote_name("new\nline"), "$'new\\nline'");
+        assert_eq!(quote_name("tab\there"), "$'tab\\there'");
+        assert_eq!(quote_name("\x1b[31mred"), "$'\\e[31mred'");
+        assert_eq!(quote_name("it's\x07"), "$'it\\'s\\x07'");
+        assert_eq!(quote_name("back\\slash\r"), "$'back\\\\slash\\r'");
+    }
+
+    #[test]
+    fn measures_visible_length_without_escape_sequences() {
+        assert_eq!(visible_length("café"), 4);
+        assert_eq!(visible_length("\x1b[1;31mred\x1b[0m"), 3);
+        assert_eq!(
+            visible_length("\x1b]8;;file:///tmp\x1b\\tmp\x1b]8;;\x1b\\"),
+            3
+        );
+        assert_eq!(
+            visible_length("\x1b]1337;File=inline=1:AAAA\x07 img"),
+            thumbnails::WIDTH + 4
+        );
+    }
+
+    #[test]
+    fn truncates_names_in_the_middle() {
+        assert_eq!(truncate_middle("abcdefghij", 10), "abcdefghij");
+        assert_eq!(truncate_middle("abcdefghij", 5), "ab…ij");
+        assert_eq!(truncate_middle("abcdefghij", 6), "abc…ij");
+        assert_eq!(truncate_middle("abcdefghij", 1), "…");
+        assert_eq!(truncate_middle("ééééé", 3), "é…é");
+    }
+
+    #[test]
+    fn keeps_escape_sequences_when_truncating() {
+        assert_eq!(
+            truncate_middle("\x1b[31mabcdefghij\x1b[0m", 5),
+            "\x1b[31mab…ij\x1b[0m"
+        );
+        assert_eq!(
+            truncate_middle("\x1b]8;;file:///x\x1b\\abcdefghij\x1b]8;;\x1b\\", 5),
+            "\x1b]8;;file:///x\x1b\\ab…ij\x1b]8;;\x1b\\"
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_format_fields() {
+        assert!(parse_format("{name} {size:>8}").is_ok());
+
+        let error = parse_format("{name} {owner}").unwrap_err();
+
+        assert!(error.starts_with(r#"unknown field "owner""#), "{error}");
+    }
+
+    #[test]
+    fn formats_special_permission_bits() {
+        assert_eq!(format_mode('-', 0o4755), "-rwsr-xr-x");
+        assert_eq!(format_mode('-', 0o4644), "-rwSr--r--");
+        assert_eq!(format_mode('-', 0o2755), "-rwxr-sr-x");
+        assert_eq!(format_mode('-', 0o2644), "-rw-r-Sr--");
+        assert_eq!(format_mode('d', 0o1777), "drwxrwxrwt");
+        assert_eq!(format_mode('d', 0o1770), "drwxrwx--T");
+        assert_eq!(format_mode('-', 0o7777), "-rwsrwsrwt");
+        assert_eq!(format_mode('-', 0o7000), "---S--S--T");
+    }
+
+    #[test]
+    fn measures_the_width_of_size_units() {
+        assert_eq!(get_unit_width("5B"), 1);
+        assert_eq!(get_unit_width("1.5KB"), 2);
+        assert_eq!(get_unit_width("2.0MiB"), 3);
+        assert_eq!(get_unit_width("\x1b[32m123.5KB\x1b[0m"), 2);
+        assert_eq!(get_unit_width("1,500"), 0);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn lists_only_broken_links_recursively() {
+        let dir = TempDir::new();
+        let file = dir.write("sub/file.txt", "");
+        std::os::unix::fs::symlink(&file, dir.path().join("sub/working")).unwrap();
+        std::os::unix::fs::symlink("missing", dir.path().join("sub/dangling")).unwrap();
+        std::os::unix::fs::symlink("missing", dir.path().join("dead")).unwrap();
+        dir.write("other/file.txt", "");
+
+        let mut config = default_config();
+        config.colorize = false;
+        config.icons = false;
+
+        let expected = [
+            ("-R", "  dead -> missing\n\nsub:\n  dangling -> missing\n"),
+            (
+                "--tree",
+                "  dead -> missing\n  sub/\n  └── dangling -> missing\n",
+            ),
+        ];
+
+        for (flag, expected) in expected {
+            let path = dir.path().to_str().unwrap();
+            let cmd = Cmd::parse_from(["ll", "-1", flag, "--broken-links", path]);
+            let mut output = vec![];
+            show_path(&mut output, &cmd, &config).unwrap();
+
+            assert_eq!(String::from_utf8(output).unwrap(), expected, "{flag}");
+        }
+    }
+
+    #[test]
+    fn detects_icons_by_content_type_only_from_contents() {
+        let dir = TempDir::new();
+        let config = default_config();
+        let icon = |path: &Path| {
+            let metadata = fs::metadata(path).unwrap();
+            get_file_icon(&config, Some(&metadata), Path::new("image/png"), path)
+        };
+
+        let text = dir.write("image/png", "not an image\n");
+        assert_eq!(icon(&text), config.files["file"]);
+
+        let png = dir.write("photo", b"\x89PNG\r\n\x1a\n....");
+        assert_eq!(icon(&png), config.aliases["image"]);
+    }
+
+    #[test]
+    fn does_not_use_link_icons_for_files_named_symlink() {
+        let dir = TempDir::new();
+        let config = default_config();
+        let path = dir.write("symlink", "");
+        let metadata = fs::metadata(&path).unwrap();
+
+        assert_eq!(
+            get_file_icon(&config, Some(&metadata), &path, &path),
+            config.files["file"]
+        );
+        assert_eq!(
+            get_link_icon(&config, &metadata),
+            config.kinds["file_symlink"]
+        );
+    }
+
+    #[test]
+    fn does_not_use_the_sparse_icon_for_files_named_sparse_file() {
+        let dir = TempDir::new();
+        let config = default_config();
+        let path = dir.write("sparse_file", "not sparse");
+        let metadata = fs::metadata(&path).unwrap();
+
+        assert_eq!(
+            get_file_icon(&config, Some(&metadata), &path, &path),
+            config.files["file"]
+        );
+    }
+}
@@ -0,0 +1,442 @@
+use image::{ImageFormat, ImageReader};
+use std::{
+    fs::File,
+    io::{self, Read, Seek, SeekFrom},
+    path::Path,
+    time::Duration,
+};
+
+/// Describe a media file, like an image's dimensions (e.g. `1920x1080`) or an
+/// audio file's duration (e.g. `3:25`). Only the file's headers are read, so
+/// it stays fast even for large files. Returns `None` for files that are not
+/// media, or can't be parsed.
+pub fn info(path: &Path) -> Option<String> {
+    let ext = path.extension()?.to_string_lossy().to_lowercase();
+
+    let duration = match ext.as_str() {
+        "mp3" => mp3_duration(path),
+        "flac" => flac_duration(path),
+        "mp4" | "m4a" | "m4v" | "mov" => mp4_duration(path),
+        "mkv" | "webm" => mkv_duration(path),
+        _ => {
+            return image_dimensions(path).map(|(width, height)| format!("{width}x{height}"));
+        }
+    };
+
+    duration.ok().flatten().map(format_duration)
+}
+
+fn image_dimensions(path: &Path) -> Option<(u32, u32)> {
+    // Only files with a known image extension are opened.
+    let format = ImageFormat::from_path(path).ok()?;
+    let mut reader = ImageReader::open(path).ok()?;
+    reader.set_format(format);
+
+    reader.into_dimensions().ok()
+}
+
+/// Format a duration like `3:25`, or `1:02:03` when it's longer than an hour.
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+
+    if hours > 0 {
+        format!("{hours}:{minutes:02}:{seconds:02}")
+    } else {
+        format!("{minutes}:{seconds:02}")
+    }
+}
+
+fn read_bytes<const N: usize>(reader: &mut impl Read) -> io::Result<[u8; N]> {
+    let mut buffer = [0; N];
+    reader.read_exact(&mut buffer)?;
+
+    Ok(buffer)
+}
+
+/// FLAC files start with a STREAMINFO block, which has the sample rate and the
+/// total number of samples.
+fn flac_duration(path: &Path) -> io::Result<Option<Duration>> {
+    let mut file = File::open(path)?;
+
+    // The `fLaC` marker, followed by the block header (type and length).
+    if &read_bytes::<4>(&mut file)? != b"fLaC" || read_bytes::<4>(&mut file)?[0] & 0x7f != 0 {
+        return Ok(None);
+    }
+
+    let info = read_bytes::<18>(&mut file)?;
+    let sample_rate =
+        u32::from(info[10]) << 12 | u32::from(info[11]) << 4 | u32::from(info[12]) >> 4;
+    let samples = u64::from(info[13] & 0x0f) << 32
+        | u64::from(u32::from_be_bytes([info[14], info[15], info[16], info[17]]));
+
+    if sample_rate == 0 {
+        return Ok(None);
+    }
+
+    Ok(Duration::try_from_secs_f64(samples as f64 / f64::from(sample_rate)).ok())
+}
+
+/// MP4 files have a `moov` box with a `mvhd` box inside it, which has the
+/// duration of the whole movie. Boxes are skipped over, so `mdat` (with the
+/// actual media) is never read.
+fn mp4_duration(path: &Path) -> io::Result<Option<Duration>> {
+    let mut file = File::open(path)?;
+    let length = file.metadata()?.len();
+
+    let Some((start, end)) = find_mp4_box(&mut file, 0, length, b"moov")? else {
+        return Ok(None);
+    };
+
+    let Some((start, _)) = find_mp4_box(&mut file, start, end, b"mvhd")? else {
+        return Ok(None);
+    };
+
+    file.seek(SeekFrom::Start(start))?;
+    let version = read_bytes::<4>(&mut file)?[0];
+
+    let (timescale, duration) = if version == 1 {
+        file.seek(SeekFrom::Current(16))?;
+        let timescale = u32::from_be_bytes(read_bytes(&mut file)?);
+        (timescale, u64::from_be_bytes(read_bytes(&mut file)?))
+    } else {
+        file.seek(SeekFrom::Current(8))?;
+        let timescale = u32::from_be_bytes(read_bytes(&mut file)?);
+        (timescale, u32::from_be_bytes(read_bytes(&mut file)?).into())
+    };
+
+    if timescale == 0 {
+        return Ok(None);
+    }
+
+    Ok(Duration::try_from_secs_f64(duration as f64 / f64::from(timescale)).ok())
+}
+
+/// Find a box with the given type between `start` and `end`, returning where
+/// its contents start and end.
+fn find_mp4_box(
+    file: &mut File,
+    mut start: u64,
+    end: u64,
+    kind: &[u8; 4],
+) -> io::Result<Option<(u64, u64)>> {
+    while end.saturating_sub(start) >= 8 {
+        file.seek(SeekFrom::Start(start))?;
+        let size = u64::from(u32::from_be_bytes(read_bytes(file)?));
+        let box_kind = read_bytes::<4>(file)?;
+
+        // Sizes of 1 mean a 64-bit size follows, and 0 means the box goes
+        // until the end.
+        let (header, size) = match size {
+            0 => (8, end - start),
+            1 => (16, u64::from_be_bytes(read_bytes(file)?)),
+            size => (8, size),
+        };
+
+        if size < header {
+            return Ok(None);
+        }
+
+        // Sizes are untrusted, so boxes that would go past the largest offset
+        // end the search.
+        let Some(box_end) = start.checked_add(size) else {
+            return Ok(None);
+        };
+
+        if &box_kind == kind {
+            return Ok(Some((start + header, box_end)));
+        }
+
+        start = box_end;
+    }
+
+    Ok(None)
+}
+
+const EBML_HEADER: u32 = 0x1a45_dfa3;
+const EBML_SEGMENT: u32 = 0x1853_8067;
+const EBML_INFO: u32 = 0x1549_a966;
+const EBML_CLUSTER: u32 = 0x1f43_b675;
+const EBML_TIMESTAMP_SCALE: u32 = 0x002a_d7b1;
+const EBML_DURATION: u32 = 0x4489;
+
+/// Matroska (and WebM) files have an `Info` element inside the `Segment`,
+/// which has the duration and the scale it's expressed in.
+fn mkv_duration(path: &Path) -> io::Result<Option<Duration>> {
+    let mut file = File::open(path)?;
+
+    // The EBML header is skipped, as the extension already tells the format.
+    let (id, size) = read_ebml_element(&mut file)?;
+    if id != EBML_HEADER {
+        return Ok(None);
+    }
+    file.seek(SeekFrom::Current(size.unwrap_or_default() as i64))?;
+
+    let (id, _) = read_ebml_element(&mut file)?;
+    if id != EBML_SEGMENT {
+        return Ok(None);
+    }
+
+    // Look for `Info` among the segment's children, which comes before the
+    // clusters with the actual media.
+    let info_size = loop {
+        match read_ebml_element(&mut file)? {
+            (EBML_INFO, Some(size)) => break size,
+            (EBML_CLUSTER, _) | (_, None) => return Ok(None),
+            (_, Some(size)) => file.seek(SeekFrom::Current(size as i64))?,
+        };
+    };
+
+    let end = file.stream_position()? + info_size;
+    let mut scale = 1_000_000;
+    let mut duration = None;
+
+    while file.stream_position()? < end {
+        let (id, Some(size)) = read_ebml_element(&mut file)? else {
+            return Ok(None);
+        };
+
+        // Only numbers are needed, which have at most 8 bytes.
+        if size > 8 {
+            file.seek(SeekFrom::Current(size as i64))?;
+            continue;
+        }
+
+        let mut value = vec![0; size as usize];
+        file.read_exact(&mut value)?;
+
+        match (id, value.len()) {
+            (EBML_TIMESTAMP_SCALE, _) => {
+                scale = value
+                    .iter()
+                    .fold(0, |scale, byte| scale << 8 | u64::from(*byte));
+            }
+            (EBML_DURATION, 4) => {
+                duration = Some(f64::from(f32::from_be_bytes(value.try_into().unwrap())));
+            }
+            (EBML_DURATION, 8) => {
+                duration = Some(f64::from_be_bytes(value.try_into().unwrap()));
+            }
+            _ => {}
+        }
+    }
+
+    // The duration is expressed in units of `scale` nanoseconds.
+    Ok(duration
+        .filter(|duration| duration.is_finite() && *duration >= 0.0)
+        .and_then(|duration| Duration::try_from_secs_f64(duration * scale as f64 / 1e9).ok()))
+}
+
+/// Read an element's ID and size. Unknown sizes (all bits set) are `None`.
+fn read_ebml_element(reader: &mut impl Read) -> io::Result<(u32, Option<u64>)> {
+    let (id, id_length) = read_ebml_varint(reader)?;
+    let (size, length) = read_ebml_varint(reader)?;
+
+    // IDs keep their length marker, while sizes don't.
+    let id = (id | 1 << (7 * id_length)) as u32;
+
+    let unknown = size == (1 << (7 * length)) - 1;
+
+    Ok((id, (!unknown).then_some(size)))
+}
+
+/// Read a variable-length integer, where the number of leading zeros in the
+/// first byte tells how many bytes follow. Returns the value (without the
+/// length marker) and the number of bytes read.
+fn read_ebml_varint(reader: &mut impl Read) -> io::Result<(u64, u32)> {
+    let first = read_bytes::<1>(reader)?[0];
+    let length = first.leading_zeros() + 1;
+
+    if length > 8 {
+        return Err(io::ErrorKind::InvalidData.into());
+    }
+
+    let mut value = u64::from(first) & (0xff >> length);
+
+    for _ in 1..length {
+        value = value << 8 | u64::from(read_bytes::<1>(reader)?[0]);
+    }
+
+    Ok((value, length))
+}
+
+/// MP3 files are a sequence of frames, optionally preceded by ID3 tags. The
+/// first frame may have a Xing (or Info) header with the number of frames;
+/// otherwise the duration is estimated from the bitrate.
+fn mp3_duration(path: &Path) -> io::Result<Option<Duration>> {
+    let mut file = File::open(path)?;
+    let length = file.metadata()?.len();
+
+    let mut start = 0;
+    let header = read_bytes::<10>(&mut file)?;
+
+    // ID3v2 tags have their size encoded with 7 bits per byte.
+    if &header[0..3] == b"ID3" {
+        let size = header[6..10]
+            .iter()
+            .fold(0, |size, byte| size << 7 | u64::from(byte & 0x7f));
+        start = 10 + size;
+    }
+
+    file.seek(SeekFrom::Start(start))?;
+    let frame = read_bytes::<4>(&mut file)?;
+
+    if frame[0] != 0xff || frame[1] & 0xe0 != 0xe0 {
+        return Ok(None);
+    }
+
+    // Only MPEG-1 (and 2/2.5) Layer III is supported.
+    let version = (frame[1] >> 3) & 0x03;
+    let layer = (frame[1] >> 1) & 0x03;
+    let bitrate_index = usize::from(frame[2] >> 4);
+    let sample_rate_index = usize::from((frame[2] >> 2) & 0x03);
+    let mono = frame[3] >> 6 == 0x03;
+
+    if layer != 0x01 || version == 0x01 || sample_rate_index == 3 {
+        return Ok(None);
+    }
+
+    let mpeg1 = version == 0x03;
+    let sample_rate = match version {
+        0x03 => [44100, 48000, 32000][sample_rate_index],
+        0x02 => [22050, 24000, 16000][sample_rate_index],
+        _ => [11025, 12000, 8000][sample_rate_index],
+    };
+    let samples_per_frame = if mpeg1 { 1152 } else { 576 };
+
+    // The Xing header comes after the side information, whose size depends
+    // on the version and the number of channels.
+    let side_info = match (mpeg1, mono) {
+        (true, false) => 32,
+        (true, true) | (false, false) => 17,
+        (false, true) => 9,
+    };
+
+    file.seek(SeekFrom::Start(start + 4 + side_info))?;
+    let xing = read_bytes::<12>(&mut file)?;
+    let has_frames = xing[7] & 0x01 != 0;
+
+    if (&xing[0..4] == b"Xing" || &xing[0..4] == b"Info") && has_frames {
+        let frames = u32::from_be_bytes([xing[8], xing[9], xing[10], xing[11]]);
+
+        return Ok(Duration::try_from_secs_f64(
+            f64::from(frames) * f64::from(samples_per_frame) / f64::from(sample_rate),
+        )
+        .ok());
+    }
+
+    let bitrates: [u32; 16] = if mpeg1 {
+        [
+            0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 0,
+        ]
+    } else {
+        [
+            0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160, 0,
+        ]
+    };
+    let bitrate = bitrates[bitrate_index] * 1000;
+
+    if bitrate == 0 {
+        return Ok(None);
+    }
+
+    Ok(Duration::try_from_secs_f64((length - start) as f64 * 8.0 / f64::from(bitrate)).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    fn mp4_box(kind: &[u8; 4], contents: &[u8]) -> Vec<u8> {
+        let mut output = (8 + contents.len() as u32).to_be_bytes().to_vec();
+        output.extend(kind);
+        output.extend(contents);
+
+        output
+    }
+
+    fn mvhd_v1(timescale: u32, duration: u64) -> Vec<u8> {
+        let mut contents = vec![1, 0, 0, 0];
+        contents.extend([0; 16]);
+        contents.extend(timescale.to_be_bytes());
+        contents.extend(duration.to_be_bytes());
+
+        mp4_box(b"mvhd", &contents)
+    }
+
+    fn mkv(scale: u64, duration: f64) -> Vec<u8> {
+        let mut info = vec![0x2a, 0xd7, 0xb1, 0x88];
+        info.extend(scale.to_be_bytes());
+        info.extend([0x44, 0x89, 0x88]);
+        info.extend(duration.to_be_bytes());
+
+        // An empty EBML header, followed by a segment of unknown size.
+        let mut output = vec![0x1a, 0x45, 0xdf, 0xa3, 0x80, 0x18, 0x53, 0x80, 0x67, 0xff];
+        output.extend([0x15, 0x49, 0xa9, 0x66, 0x80 | info.len() as u8]);
+        output.extend(info);
+
+        output
+    }
+
+    #[test]
+    fn formats_durations() {
+        assert_eq!(format_duration(Duration::from_secs(205)), "3:25");
+        assert_eq!(format_duration(Duration::from_secs(3723)), "1:02:03");
+        assert_eq!(format_duration(Duration::ZERO), "0:00");
+    }
+
+    #[test]
+    fn reads_mp4_durations() {
+        let dir = TempDir::new();
+        let path = dir.write("movie.mp4", mp4_box(b"moov", &mvhd_v1(1000, 205_000)));
+
+        assert_eq!(info(&path), Some("3:25".to_string()));
+    }
+
+    #[test]
+    fn ignores_mp4_durations_that_overflow() {
+        let dir = TempDir::new();
+        let path = dir.write("overflow.mp4", mp4_box(b"moov", &mvhd_v1(1, u64::MAX)));
+
+        assert_eq!(info(&path), None);
+    }
+
+    #[test]
+    fn stops_at_mp4_boxes_past_the_largest_offset() {
+        // A box with a 64-bit size, followed by the one being looked for.
+        let mut contents = 1u32.to_be_bytes().to_vec();
+        contents.extend(b"free");
+        contents.extend(u64::MAX.to_be_bytes());
+        contents.extend(mp4_box(b"moov", &mvhd_v1(1, 1)));
+
+        let dir = TempDir::new();
+        let path = dir.write("hostile.mp4", contents);
+
+        assert_eq!(info(&path), None);
+    }
+
+    #[test]
+    fn reads_mkv_durations() {
+        let dir = TempDir::new();
+        let path = dir.write("movie.mkv", mkv(1_000_000, 205_000.0));
+
+        assert_eq!(info(&path), Some("3:25".to_string()));
+    }
+
+    #[test]
+    fn ignores_mkv_durations_that_overflow() {
+        let dir = TempDir::new();
+        let path = dir.write("overflow.mkv", mkv(u64::MAX, 1e300));
+
+        assert_eq!(info(&path), None);
+    }
+
+    #[test]
+    fn ignores_truncated_files() {
+        let dir = TempDir::new();
+        let path = dir.write("truncated.flac", b"fLaC\0\0");
+
+        assert_eq!(info(&path), None);
+    }
+}
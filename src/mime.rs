@@ -0,0 +1,280 @@
+use std::{
+    fs::{self, File},
+    io::Read,
+    path::Path,
+};
+
+/// How many bytes are read from the beginning of a file to detect its type.
+const SNIFF_LENGTH: usize = 512;
+
+/// Well-known signatures, as the offset where they appear, the bytes
+/// themselves, and the content type they identify. Short signatures that text
+/// files may also start with are confirmed by `has_valid_header`.
+const SIGNATURES: &[(usize, &[u8], &str)] = &[
+    (0, b"\x89PNG\r\n\x1a\n", "image/png"),
+    (0, b"\xff\xd8\xff", "image/jpeg"),
+    (0, b"GIF87a", "image/gif"),
+    (0, b"GIF89a", "image/gif"),
+    (0, b"BM", "image/bmp"),
+    (8, b"WEBP", "image/webp"),
+    (0, b"%PDF-", "application/pdf"),
+    (0, b"PK\x03\x04", "application/zip"),
+    (0, b"\x1f\x8b", "application/gzip"),
+    (0, b"BZh", "application/x-bzip2"),
+    (0, b"\xfd7zXZ\x00", "application/x-xz"),
+    (0, b"7z\xbc\xaf\x27\x1c", "application/x-7z-compressed"),
+    (257, b"ustar", "application/x-tar"),
+    (0, b"\x7fELF", "application/x-executable"),
+    (0, b"\xcf\xfa\xed\xfe", "application/x-mach-binary"),
+    (0, b"\xce\xfa\xed\xfe", "application/x-mach-binary"),
+    (0, b"\xca\xfe\xba\xbe", "application/x-mach-binary"),
+    (0, b"MZ", "application/x-msdownload"),
+    (0, b"\x00asm", "application/wasm"),
+    (0, b"SQLite format 3\x00", "application/vnd.sqlite3"),
+    (0, b"ID3", "audio/mpeg"),
+    (0, b"OggS", "audio/ogg"),
+    (0, b"fLaC", "audio/flac"),
+    (4, b"ftyp", "video/mp4"),
+];
+
+/// Interpreters commonly found in shebangs, and the content type of the
+/// scripts they run.
+const INTERPRETERS: &[(&str, &str)] = &[
+    ("bash", "text/x-shellscript"),
+    ("dash", "text/x-shellscript"),
+    ("fish", "text/x-shellscript"),
+    ("ksh", "text/x-shellscript"),
+    ("sh", "text/x-shellscript"),
+    ("zsh", "text/x-shellscript"),
+    ("node", "text/javascript"),
+    ("perl", "text/x-perl"),
+    ("php", "text/x-php"),
+    ("python", "text/x-python"),
+    ("python3", "text/x-python"),
+    ("ruby", "text/x-ruby"),
+];
+
+/// Whether the file looks binary, judging by its first bytes. Empty files and
+/// files that can't be read are not considered binary.
+pub fn is_binary(path: &Path) -> bool {
+    read_start(path).is_some_and(|buffer| !is_text(&buffer))
+}
+
+/// Detect a file's content type from its first bytes, rather than from its
+/// extension. Returns `None` when the file can't be read.
+pub fn detect(path: &Path) -> Option<&'static str> {
+    let buffer = read_start(path)?;
+
+    let signature = SIGNATURES.iter().find(|(offset, magic, mime)| {
+        buffer
+            .get(*offset..offset + magic.len())
+            .is_some_and(|bytes| bytes == *magic)
+            && has_valid_header(&buffer, mime)
+    });
+
+    if let Some((_, _, mime)) = signature {
+        return Some(mime);
+    }
+
+    if let Some(mime) = detect_script(&buffer) {
+        return Some(mime);
+    }
+
+    if buffer.is_empty() {
+        Some("inode/x-empty")
+    } else if is_text(&buffer) {
+        Some("text/plain")
+    } else {
+        Some("application/octet-stream")
+    }
+}
+
+/// Read the first bytes of a regular file. Other files (e.g. FIFOs) are not
+/// read, as reading them could block or consume their data.
+fn read_start(path: &Path) -> Option<Vec<u8>> {
+    if !fs::metadata(path).is_ok_and(|metadata| metadata.is_file()) {
+        return None;
+    }
+
+    let mut buffer = Vec::with_capacity(SNIFF_LENGTH);
+    File::open(path)
+        .ok()?
+        .take(SNIFF_LENGTH as u64)
+        .read_to_end(&mut buffer)
+        .ok()?;
+
+    Some(buffer)
+}
+
+/// Check the headers of formats whose signature is only two bytes (e.g. a text
+/// file starting with "BM" or "MZ").
+fn has_valid_header(buffer: &[u8], mime: &str) -> bool {
+    let read_u32 = |offset: usize| -> Option<u32> {
+        let bytes = buffer.get(offset..offset + 4)?;
+        Some(u32::from_le_bytes(bytes.try_into().ok()?))
+    };
+
+    match mime {
+        // The size of the DIB header, which depends on its version.
+        "image/bmp" => read_u32(14).is_some_and(|size| matches!(size, 12 | 40 | 108 | 124)),
+        // The DOS header points to the PE header, which must be within the bytes
+        // read.
+        "application/x-msdownload" => read_u32(0x3c)
+            .and_then(|offset| buffer.get(usize::try_from(offset).ok()?..))
+            .is_some_and(|header| header.starts_with(b"PE\0\0")),
+        _ => true,
+    }
+}
+
+/// Detect scripts by the interpreter in their shebang, like `#!/bin/sh` or
+/// `#!/usr/bin/env ruby`.
+fn detect_script(buffer: &[u8]) -> Option<&'static str> {
+    let shebang = buffer.strip_prefix(b"#!")?;
+    let line = shebang.split(|byte| *byte == b'\n').next()?;
+    let line = String::from_utf8_lossy(line);
+    let mut args = line.split_whitespace();
+
+    let mut program = args.next()?.rsplit('/').next()?;
+
+    if program == "env" {
+        program = args.find(|arg| !arg.starts_with('-'))?;
+    }
+
+    // Versioned interpreters (e.g. `python3.12`) are matched by their name.
+    let name = program.trim_end_matches(|char: char| char.is_ascii_digit() || char == '.');
+
+    INTERPRETERS
+        .iter()
+        .find(|(interpreter, _)| *interpreter == program || *interpreter == name)
+        .map(|(_, mime)| *mime)
+}
+
+/// Text files have no NUL bytes and are valid UTF-8. The buffer may end in the
+/// middle of a character, which is fine.
+fn is_text(buffer: &[u8]) -> bool {
+    if buffer.contains(&0) {
+        return false;
+    }
+
+    match std::str::from_utf8(buffer) {
+        Ok(_) => true,
+        Err(error) => error.error_len().is_none(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    fn detect_content(name: &str, content: &[u8]) -> Option<&'static str> {
+        let dir = TempDir::new();
+
+        detect(&dir.write(name, content))
+    }
+
+    #[test]
+    fn detects_signatures() {
+        assert_eq!(
+            detect_content("png", b"\x89PNG\r\n\x1a\n...."),
+            Some("image/png")
+        );
+        assert_eq!(
+            detect_content("webp", b"RIFF\0\0\0\0WEBPVP8 "),
+            Some("image/webp")
+        );
+        assert_eq!(
+            detect_content("mp4", b"\0\0\0\x20ftypisom"),
+            Some("video/mp4")
+        );
+
+        let mut bmp = vec![0; 54];
+        bmp[..2].copy_from_slice(b"BM");
+        bmp[14] = 40;
+        assert_eq!(detect_content("bmp", &bmp), Some("image/bmp"));
+
+        let mut exe = vec![0; 128];
+        exe[..2].copy_from_slice(b"MZ");
+        exe[0x3c] = 0x40;
+        exe[0x40..0x44].copy_from_slice(b"PE\0\0");
+        assert_eq!(
+            detect_content("exe", &exe),
+            Some("application/x-msdownload")
+        );
+
+        let mut tar = vec![0; 512];
+        tar[257..262].copy_from_slice(b"ustar");
+        assert_eq!(detect_content("tar", &tar), Some("application/x-tar"));
+    }
+
+    #[test]
+    fn checks_the_headers_of_short_signatures() {
+        assert_eq!(
+            detect_content("bmp", b"BMW drivers manual\n"),
+            Some("text/plain")
+        );
+        assert_eq!(
+            detect_content("exe", b"MZ is a two-letter code\n"),
+            Some("text/plain")
+        );
+
+        let mut exe = vec![0; 128];
+        exe[..2].copy_from_slice(b"MZ");
+        exe[0x3c] = 0xff;
+        assert_eq!(
+            detect_content("exe", &exe),
+            Some("application/octet-stream")
+        );
+    }
+
+    #[test]
+    fn detects_text_and_binary_content() {
+        assert_eq!(detect_content("empty", b""), Some("inode/x-empty"));
+        assert_eq!(
+            detect_content("text", "héllo\n".as_bytes()),
+            Some("text/plain")
+        );
+        assert_eq!(
+            detect_content("nul", b"a\0b"),
+            Some("application/octet-stream")
+        );
+        assert_eq!(
+            detect_content("latin1", b"caf\xe9!"),
+            Some("application/octet-stream")
+        );
+    }
+
+    #[test]
+    fn does_not_detect_missing_files_or_directories() {
+        assert_eq!(detect(Path::new("/this/does/not/exist")), None);
+        assert_eq!(detect(TempDir::new().path()), None);
+    }
+
+    #[test]
+    fn detects_scripts_by_shebang() {
+        assert_eq!(
+            detect_script(b"#!/bin/sh\necho"),
+            Some("text/x-shellscript")
+        );
+        assert_eq!(detect_script(b"#!/usr/bin/env ruby"), Some("text/x-ruby"));
+        assert_eq!(
+            detect_script(b"#!/usr/bin/env -S node --flag"),
+            Some("text/javascript")
+        );
+        assert_eq!(
+            detect_script(b"#!/usr/bin/python3.12 -u"),
+            Some("text/x-python")
+        );
+        assert_eq!(detect_script(b"#!/usr/bin/awk -f"), None);
+        assert_eq!(detect_script(b"#!"), None);
+        assert_eq!(detect_script(b"#!/usr/bin/env"), None);
+        assert_eq!(detect_script(b"echo"), None);
+    }
+
+    #[test]
+    fn allows_text_cut_in_the_middle_of_a_character() {
+        assert!(is_text("é".as_bytes()));
+        assert!(is_text(&"é".as_bytes()[..1]));
+        assert!(!is_text(b"\xff\xfe"));
+    }
+}
@@ -0,0 +1,111 @@
+use crossterm::terminal;
+use std::{
+    env,
+    io::{self, IsTerminal, Write},
+    process::{Command, Stdio},
+};
+
+/// Write the output to stdout, piping it through `$PAGER` (or `less -R`) when
+/// it doesn't fit the terminal. `width` returns how many columns a line takes,
+/// so lines longer than the terminal are counted as the rows they wrap into.
+pub fn show(output: &[u8], width: impl Fn(&str) -> usize) -> io::Result<()> {
+    let mut stdout = io::stdout();
+
+    // One row is kept for the shell prompt, displayed after the output.
+    let fits = match terminal::size() {
+        Ok((columns, height)) => {
+            let text = String::from_utf8_lossy(output);
+            count_rows(&text, columns.into(), width) < height.into()
+        }
+        Err(_) => true,
+    };
+
+    if fits || !stdout.is_terminal() {
+        return stdout.write_all(output);
+    }
+
+    let pager = env::var("PAGER")
+        .ok()
+        .filter(|pager| !pager.trim().is_empty())
+        .unwrap_or_else(|| "less -R".to_string());
+    let mut args = pager.split_whitespace();
+    let program = args.next().unwrap_or("less");
+
+    // `less` needs `-R` to display colors, so make sure it's set even when the
+    // pager is configured without it.
+    let less = with_raw_control_chars(&env::var("LESS").unwrap_or_default());
+
+    let Ok(mut child) = Command::new(program)
+        .args(args)
+        .env("LESS", less)
+        .stdin(Stdio::piped())
+        .spawn()
+    else {
+        return stdout.write_all(output);
+    };
+
+    if let Some(mut stdin) = child.stdin.take() {
+        // The pager may be closed before reading everything, which is fine.
+        stdin.write_all(output).ok();
+    }
+
+    child.wait()?;
+
+    Ok(())
+}
+
+/// Count the terminal rows the output takes, including the ones lines wrap
+/// into.
+fn count_rows(output: &str, columns: usize, width: impl Fn(&str) -> usize) -> usize {
+    output
+        .lines()
+        .map(|line| width(line).div_ceil(columns.max(1)).max(1))
+        .sum()
+}
+
+/// Add `-R` to the `LESS` options, unless they already display colors (with
+/// `-R` or `-r`, in short or long form).
+fn with_raw_control_chars(less: &str) -> String {
+    let has_raw = less.split_whitespace().any(|option| match option {
+        "--RAW-CONTROL-CHARS" | "--raw-control-chars" => true,
+        option if option.starts_with("--") => false,
+        option => option.contains(['R', 'r']),
+    });
+
+    match less.trim() {
+        _ if has_raw => less.to_string(),
+        "" => "R".to_string(),
+        less => format!("{less} -R"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_wrapped_lines_as_several_rows() {
+        let width = |line: &str| line.chars().count();
+
+        assert_eq!(count_rows("a\nb\n", 80, width), 2);
+        assert_eq!(count_rows(&format!("{}\n\n", "x".repeat(81)), 80, width), 3);
+        assert_eq!(count_rows(&format!("{}\n", "x".repeat(80)), 80, width), 1);
+    }
+
+    #[test]
+    fn adds_raw_control_chars_to_less_options() {
+        assert_eq!(with_raw_control_chars(""), "R");
+        assert_eq!(with_raw_control_chars("-F"), "-F -R");
+        assert_eq!(with_raw_control_chars("FX"), "FX -R");
+        assert_eq!(
+            with_raw_control_chars("--quit-if-one-screen"),
+            "--quit-if-one-screen -R"
+        );
+        assert_eq!(with_raw_control_chars("-FRX"), "-FRX");
+        assert_eq!(with_raw_control_chars("-r"), "-r");
+        assert_eq!(
+            with_raw_control_chars("--RAW-CONTROL-CHARS"),
+            "--RAW-CONTROL-CHARS"
+        );
+    }
+}
@@ -0,0 +1,216 @@
+/// How a field is aligned within its width.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Align {
+    Left,
+    Right,
+    Center,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Part {
+    Text(String),
+    Field {
+        name: String,
+        align: Align,
+        width: usize,
+    },
+}
+
+/// A line template like `{icon} {name:<30} {size:>8}`. Fields can be padded
+/// to a width, aligned to the left (`<`, the default), right (`>`), or center
+/// (`^`). Braces are written as `{{` and `}}`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+impl Template {
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let mut parts = vec![];
+        let mut text = String::new();
+        let mut chars = input.chars().peekable();
+
+        while let Some(char) = chars.next() {
+            match char {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '{' => {
+                    let mut field = String::new();
+
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(char) => field.push(char),
+                            None => return Err("unclosed `{` in template".to_string()),
+                        }
+                    }
+
+                    if !text.is_empty() {
+                        parts.push(Part::Text(std::mem::take(&mut text)));
+                    }
+
+                    parts.push(parse_field(&field)?);
+                }
+                '}' => return Err("unmatched `}` in template (use `}}` for a brace)".to_string()),
+                char => text.push(char),
+            }
+        }
+
+        if !text.is_empty() {
+            parts.push(Part::Text(text));
+        }
+
+        Ok(Self { parts })
+    }
+
+    /// The names of the fields used by the template.
+    pub fn fields(&self) -> impl Iterator<Item = &str> {
+        self.parts.iter().filter_map(|part| match part {
+            Part::Field { name, .. } => Some(name.as_str()),
+            Part::Text(_) => None,
+        })
+    }
+
+    /// Render the template, getting each field's value from `value`. Padding
+    /// is based on `width`, so values can have escape sequences (e.g. colors).
+    pub fn render(&self, value: impl Fn(&str) -> String, width: impl Fn(&str) -> usize) -> String {
+        let mut output = String::new();
+
+        for part in &self.parts {
+            match part {
+                Part::Text(text) => output.push_str(text),
+                Part::Field {
+                    name,
+                    align,
+                    width: min_width,
+                } => {
+                    let value = value(name);
+                    let padding = min_width.saturating_sub(width(&value));
+                    let (before, after) = match align {
+                        Align::Left => (0, padding),
+                        Align::Right => (padding, 0),
+                        Align::Center => (padding / 2, padding - padding / 2),
+                    };
+
+                    output.push_str(&" ".repeat(before));
+                    output.push_str(&value);
+                    output.push_str(&" ".repeat(after));
+                }
+            }
+        }
+
+        output
+    }
+}
+
+/// Parse a field like `name`, `name:30`, or `size:>8`.
+fn parse_field(field: &str) -> Result<Part, String> {
+    let (name, spec) = field.split_once(':').unwrap_or((field, ""));
+    let name = name.trim();
+
+    if name.is_empty() {
+        return Err("empty field in template".to_string());
+    }
+
+    let (align, width) = match spec.chars().next() {
+        Some('<') => (Align::Left, &spec[1..]),
+        Some('>') => (Align::Right, &spec[1..]),
+        Some('^') => (Align::Center, &spec[1..]),
+        _ => (Align::Left, spec),
+    };
+
+    let width = if width.is_empty() {
+        0
+    } else {
+        width
+            .parse()
+            .map_err(|_| format!("invalid width {width:?} for the field {name:?}"))?
+    };
+
+    Ok(Part::Field {
+        name: name.to_string(),
+        align,
+        width,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(template: &str, value: &str) -> String {
+        Template::parse(template)
+            .unwrap()
+            .render(|_| value.to_string(), |value| value.chars().count())
+    }
+
+    #[test]
+    fn parses_text_and_fields() {
+        let template = Template::parse("{icon} {name:<30} {size:>8}").unwrap();
+
+        assert_eq!(
+            template.fields().collect::<Vec<_>>(),
+            ["icon", "name", "size"]
+        );
+        assert_eq!(
+            template.parts[4],
+            Part::Field {
+                name: "size".to_string(),
+                align: Align::Right,
+                width: 8
+            }
+        );
+    }
+
+    #[test]
+    fn renders_aligned_fields() {
+        assert_eq!(render("[{name}]", "ab"), "[ab]");
+        assert_eq!(render("[{name:5}]", "ab"), "[ab   ]");
+        assert_eq!(render("[{name:<5}]", "ab"), "[ab   ]");
+        assert_eq!(render("[{name:>5}]", "ab"), "[   ab]");
+        assert_eq!(render("[{name:^5}]", "ab"), "[ ab  ]");
+        assert_eq!(render("[{name:>1}]", "abc"), "[abc]");
+    }
+
+    #[test]
+    fn pads_using_the_visible_width() {
+        let template = Template::parse("{name:>4}|").unwrap();
+        let output = template.render(|_| "\x1b[1mab\x1b[0m".to_string(), |_| 2);
+
+        assert_eq!(output, "  \x1b[1mab\x1b[0m|");
+    }
+
+    #[test]
+    fn renders_escaped_braces() {
+        assert_eq!(render("{{{name}}}", "ab"), "{ab}");
+        assert_eq!(Template::parse("{{}}").unwrap().fields().count(), 0);
+    }
+
+    #[test]
+    fn rejects_malformed_templates() {
+        assert_eq!(
+            Template::parse("{name"),
+            Err("unclosed `{` in template".to_string())
+        );
+        assert_eq!(
+            Template::parse("name}"),
+            Err("unmatched `}` in template (use `}}` for a brace)".to_string())
+        );
+        assert_eq!(
+            Template::parse("{ :>5}"),
+            Err("empty field in template".to_string())
+        );
+        assert_eq!(
+            Template::parse("{name:>x}"),
+            Err(r#"invalid width "x" for the field "name""#.to_string())
+        );
+        assert!(Template::parse("{name:-1}").is_err());
+        assert!(Template::parse("{name:99999999999999999999999}").is_err());
+    }
+}
@@ -0,0 +1,91 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use image::ImageFormat;
+use std::{
+    io::{Cursor, IsTerminal},
+    path::Path,
+};
+
+/// How many terminal cells a thumbnail takes.
+pub const WIDTH: usize = 2;
+
+/// Images bigger than this are not decoded, so listings stay fast.
+const MAX_FILE_SIZE: u64 = 20 * 1024 * 1024;
+
+/// The terminal graphics protocols we know how to render thumbnails with.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Protocol {
+    Kitty,
+    Iterm,
+}
+
+/// Detect the graphics protocol supported by the terminal, if any. Thumbnails
+/// are never rendered when the output is not a terminal.
+pub fn detect() -> Option<Protocol> {
+    if !std::io::stdout().is_terminal() {
+        return None;
+    }
+
+    let env = |name: &str| std::env::var(name).unwrap_or_default();
+    let term = env("TERM");
+    let term_program = env("TERM_PROGRAM");
+
+    if std::env::var("KITTY_WINDOW_ID").is_ok()
+        || term == "xterm-kitty"
+        || term == "xterm-ghostty"
+        || term_program == "ghostty"
+    {
+        Some(Protocol::Kitty)
+    } else if term_program == "iTerm.app"
+        || term_program == "WezTerm"
+        || env("LC_TERMINAL") == "iTerm2"
+    {
+        Some(Protocol::Iterm)
+    } else {
+        None
+    }
+}
+
+/// Render a thumbnail for the given image, as an escape sequence that takes
+/// [`WIDTH`] cells. Returns `None` if the file is not an image we can decode.
+pub fn render(protocol: Protocol, path: &Path, size: u64) -> Option<String> {
+    let format = ImageFormat::from_path(path).ok()?;
+
+    if !format.reading_enabled() || size > MAX_FILE_SIZE {
+        return None;
+    }
+
+    let thumbnail = image::open(path).ok()?.thumbnail(64, 64);
+    let mut png = Cursor::new(vec![]);
+    thumbnail.write_to(&mut png, ImageFormat::Png).ok()?;
+    let data = STANDARD.encode(png.get_ref());
+
+    match protocol {
+        Protocol::Kitty => Some(render_kitty(&data)),
+        Protocol::Iterm => Some(format!(
+            "\x1b]1337;File=inline=1;width={WIDTH};height=1;preserveAspectRatio=1;size={}:{data}\x07",
+            png.get_ref().len()
+        )),
+    }
+}
+
+// The kitty protocol requires the payload to be sent in chunks of up to 4096
+// bytes.
+fn render_kitty(data: &str) -> String {
+    let chunks: Vec<&[u8]> = data.as_bytes().chunks(4096).collect();
+    let last = chunks.len().saturating_sub(1);
+
+    chunks
+        .iter()
+        .enumerate()
+        .map(|(index, chunk)| {
+            let more = u8::from(index != last);
+            let chunk = String::from_utf8_lossy(chunk);
+
+            if index == 0 {
+                format!("\x1b_Ga=T,f=100,c={WIDTH},r=1,q=2,m={more};{chunk}\x1b\\")
+            } else {
+                format!("\x1b_Gm={more};{chunk}\x1b\\")
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,240 @@
+#[cfg(unix)]
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    ffi::{CStr, CString},
+    ptr,
+};
+
+#[cfg(windows)]
+use std::{os::windows::ffi::OsStrExt, path::Path, ptr};
+
+#[cfg(unix)]
+thread_local! {
+    static USER_NAMES: RefCell<HashMap<u32, Option<String>>> = RefCell::new(HashMap::new());
+    static GROUP_NAMES: RefCell<HashMap<u32, Option<String>>> = RefCell::new(HashMap::new());
+}
+
+/// Resolve the name of the user with the given uid. Lookups are cached, as the
+/// same few users usually own all entries of a listing.
+#[cfg(unix)]
+pub fn user_name(uid: u32) -> Option<String> {
+    USER_NAMES.with(|cache| {
+        cache
+            .borrow_mut()
+            .entry(uid)
+            .or_insert_with(|| lookup_user_name(uid))
+            .clone()
+    })
+}
+
+/// Resolve the name of the group with the given gid. Lookups are cached, as
+/// the same few groups usually own all entries of a listing.
+#[cfg(unix)]
+pub fn group_name(gid: u32) -> Option<String> {
+    GROUP_NAMES.with(|cache| {
+        cache
+            .borrow_mut()
+            .entry(gid)
+            .or_insert_with(|| lookup_group_name(gid))
+            .clone()
+    })
+}
+
+#[cfg(unix)]
+fn lookup_user_name(uid: u32) -> Option<String> {
+    let mut buffer = vec![0; 1024];
+
+    loop {
+        // SAFETY: `passwd` and `result` are only read when the call succeeds,
+        // and `buffer` outlives the `CStr` we copy the name from.
+        unsafe {
+            let mut passwd: libc::passwd = std::mem::zeroed();
+            let mut result = ptr::null_mut();
+            let code = libc::getpwuid_r(
+                uid,
+                &mut passwd,
+                buffer.as_mut_ptr(),
+                buffer.len(),
+                &mut result,
+            );
+
+            if code == libc::ERANGE {
+                buffer.resize(buffer.len() * 2, 0);
+                continue;
+            }
+
+            if code != 0 || result.is_null() {
+                return None;
+            }
+
+            return Some(CStr::from_ptr(passwd.pw_name).to_string_lossy().to_string());
+        }
+    }
+}
+
+#[cfg(unix)]
+fn lookup_group_name(gid: u32) -> Option<String> {
+    let mut buffer = vec![0; 1024];
+
+    loop {
+        // SAFETY: `group` and `result` are only read when the call succeeds,
+        // and `buffer` outlives the `CStr` we copy the name from.
+        unsafe {
+            let mut group: libc::group = std::mem::zeroed();
+            let mut result = ptr::null_mut();
+            let code = libc::getgrgid_r(
+                gid,
+                &mut group,
+                buffer.as_mut_ptr(),
+                buffer.len(),
+                &mut result,
+            );
+
+            if code == libc::ERANGE {
+                buffer.resize(buffer.len() * 2, 0);
+                continue;
+            }
+
+            if code != 0 || result.is_null() {
+                return None;
+            }
+
+            return Some(CStr::from_ptr(group.gr_name).to_string_lossy().to_string());
+        }
+    }
+}
+
+/// The uid of the user running `ll`.
+#[cfg(unix)]
+pub fn current_user_id() -> u32 {
+    // SAFETY: `getuid` is always successful.
+    unsafe { libc::getuid() }
+}
+
+/// Resolve the uid of the user with the given name.
+#[cfg(unix)]
+pub fn user_id(name: &str) -> Option<u32> {
+    let name = CString::new(name).ok()?;
+    let mut buffer = vec![0; 1024];
+
+    loop {
+        // SAFETY: `name` is NUL-terminated, and `passwd` is only read when the
+        // call succeeds.
+        unsafe {
+            let mut passwd: libc::passwd = std::mem::zeroed();
+            let mut result = ptr::null_mut();
+            let code = libc::getpwnam_r(
+                name.as_ptr(),
+                &mut passwd,
+                buffer.as_mut_ptr(),
+                buffer.len(),
+                &mut result,
+            );
+
+            if code == libc::ERANGE {
+                buffer.resize(buffer.len() * 2, 0);
+                continue;
+            }
+
+            if code != 0 || result.is_null() {
+                return None;
+            }
+
+            return Some(passwd.pw_uid);
+        }
+    }
+}
+
+/// Resolve the gid of the group with the given name.
+#[cfg(unix)]
+pub fn group_id(name: &str) -> Option<u32> {
+    let name = CString::new(name).ok()?;
+    let mut buffer = vec![0; 1024];
+
+    loop {
+        // SAFETY: `name` is NUL-terminated, and `group` is only read when the
+        // call succeeds.
+        unsafe {
+            let mut group: libc::group = std::mem::zeroed();
+            let mut result = ptr::null_mut();
+            let code = libc::getgrnam_r(
+                name.as_ptr(),
+                &mut group,
+                buffer.as_mut_ptr(),
+                buffer.len(),
+                &mut result,
+            );
+
+            if code == libc::ERANGE {
+                buffer.resize(buffer.len() * 2, 0);
+                continue;
+            }
+
+            if code != 0 || result.is_null() {
+                return None;
+            }
+
+            return Some(group.gr_gid);
+        }
+    }
+}
+
+/// Resolve the name of the account that owns the given path.
+#[cfg(windows)]
+pub fn owner(path: &Path) -> Option<String> {
+    use windows_sys::Win32::{
+        Foundation::{LocalFree, ERROR_SUCCESS},
+        Security::{
+            Authorization::{GetNamedSecurityInfoW, SE_FILE_OBJECT},
+            LookupAccountSidW, OWNER_SECURITY_INFORMATION, PSECURITY_DESCRIPTOR, PSID,
+        },
+    };
+
+    let wide_path: Vec<u16> = path.as_os_str().encode_wide().chain([0]).collect();
+    let mut owner: PSID = ptr::null_mut();
+    let mut descriptor: PSECURITY_DESCRIPTOR = ptr::null_mut();
+
+    // SAFETY: `wide_path` is NUL-terminated, and the security descriptor
+    // (which owns the SID) is freed only after we're done with the SID.
+    unsafe {
+        let code = GetNamedSecurityInfoW(
+            wide_path.as_ptr(),
+            SE_FILE_OBJECT,
+            OWNER_SECURITY_INFORMATION,
+            &mut owner,
+            ptr::null_mut(),
+            ptr::null_mut(),
+            ptr::null_mut(),
+            &mut descriptor,
+        );
+
+        if code != ERROR_SUCCESS {
+            return None;
+        }
+
+        let mut name = vec![0u16; 256];
+        let mut name_len = 256;
+        let mut domain = vec![0u16; 256];
+        let mut domain_len = 256;
+        let mut sid_type = 0;
+
+        let found = LookupAccountSidW(
+            ptr::null(),
+            owner,
+            name.as_mut_ptr(),
+            &mut name_len,
+            domain.as_mut_ptr(),
+            &mut domain_len,
+            &mut sid_type,
+        );
+
+        LocalFree(descriptor);
+
+        if found == 0 {
+            return None;
+        }
+
+        Some(String::from_utf16_lossy(&name[..name_len as usize]))
+    }
+}
@@ -0,0 +1,59 @@
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher as _};
+use std::{
+    path::Path,
+    sync::mpsc::{self, Receiver},
+    thread,
+    time::Duration,
+};
+
+/// How long to wait for related events (e.g. a file being created and then
+/// written to), so they trigger a single refresh.
+const DEBOUNCE: Duration = Duration::from_millis(100);
+
+/// Waits for changes to a directory, so `--watch` can refresh as soon as
+/// entries are created, removed, or renamed.
+pub struct Watcher {
+    // Watching stops when the watcher is dropped, so it must be kept around.
+    _watcher: RecommendedWatcher,
+    changes: Receiver<()>,
+}
+
+impl Watcher {
+    /// Start watching the given directory. Returns `None` when the platform
+    /// watcher couldn't be started, so callers can fall back to polling.
+    pub fn new(dir: &Path, recursive: bool) -> Option<Self> {
+        let (sender, changes) = mpsc::channel();
+
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+            // Reading the directory (which we do on every refresh) triggers
+            // access events, so they must be ignored.
+            if event.is_ok_and(|event| !matches!(event.kind, EventKind::Access(_))) {
+                sender.send(()).ok();
+            }
+        })
+        .ok()?;
+
+        let mode = if recursive {
+            RecursiveMode::Recursive
+        } else {
+            RecursiveMode::NonRecursive
+        };
+
+        watcher.watch(dir, mode).ok()?;
+
+        Some(Self {
+            _watcher: watcher,
+            changes,
+        })
+    }
+
+    /// Block until something changes, or until the timeout expires.
+    pub fn wait(&self, timeout: Duration) {
+        if self.changes.recv_timeout(timeout).is_err() {
+            return;
+        }
+
+        thread::sleep(DEBOUNCE);
+        self.changes.try_iter().for_each(drop);
+    }
+}
@@ -0,0 +1,188 @@
+use std::path::Path;
+
+/// An extended attribute, with the size of its value in bytes.
+#[derive(Debug)]
+pub struct Attribute {
+    pub name: String,
+    pub size: usize,
+}
+
+/// List the extended attributes of a file, without following symlinks. Files
+/// that can't be read, and platforms without extended attributes, have none.
+pub fn list(path: &Path) -> Vec<Attribute> {
+    let Some(names) = sys::list(path) else {
+        return vec![];
+    };
+
+    names
+        .split(|byte| *byte == 0)
+        .filter(|name| !name.is_empty())
+        .map(|name| {
+            let name = String::from_utf8_lossy(name).to_string();
+            let size = sys::size(path, &name).unwrap_or_default();
+
+            Attribute { name, size }
+        })
+        .collect()
+}
+
+/// Read the value of an extended attribute, without following symlinks.
+pub fn get(path: &Path, name: &str) -> Option<Vec<u8>> {
+    sys::value(path, name)
+}
+
+/// Whether a file has any extended attributes, not counting the `security`
+/// namespace (e.g. SELinux labels), which every file has on some systems.
+pub fn has_any(path: &Path) -> bool {
+    sys::list(path).is_some_and(|names| has_non_security_names(&names))
+}
+
+fn has_non_security_names(names: &[u8]) -> bool {
+    names
+        .split(|byte| *byte == 0)
+        .any(|name| !name.is_empty() && !name.starts_with(b"security."))
+}
+
+#[cfg(any(target_os = "linux", target_os = "android", target_os = "macos"))]
+mod sys {
+    use std::{ffi::CString, os::unix::ffi::OsStrExt, path::Path, ptr};
+
+    /// The attribute names, each one followed by a NUL byte.
+    pub fn list(path: &Path) -> Option<Vec<u8>> {
+        let path = CString::new(path.as_os_str().as_bytes()).ok()?;
+
+        // The first call gets the size of the list, which may change before
+        // the second call, so it's retried when the buffer is too small.
+        loop {
+            // SAFETY: a null buffer with a size of 0 only asks for the size.
+            let size = unsafe { list_names(&path, ptr::null_mut(), 0) };
+
+            if size <= 0 {
+                return None;
+            }
+
+            let mut buffer = vec![0u8; size as usize];
+
+            // SAFETY: the buffer is as large as the size passed along with it.
+            let size = unsafe { list_names(&path, buffer.as_mut_ptr().cast(), buffer.len()) };
+
+            if size >= 0 {
+                buffer.truncate(size as usize);
+                return Some(buffer);
+            }
+
+            if std::io::Error::last_os_error().raw_os_error() != Some(libc::ERANGE) {
+                return None;
+            }
+        }
+    }
+
+    /// The size of an attribute's value.
+    pub fn size(path: &Path, name: &str) -> Option<usize> {
+        let path = CString::new(path.as_os_str().as_bytes()).ok()?;
+        let name = CString::new(name).ok()?;
+
+        // SAFETY: a null buffer with a size of 0 only asks for the size.
+        let size = unsafe { read_value(&path, &name, ptr::null_mut(), 0) };
+
+        usize::try_from(size).ok()
+    }
+
+    /// The value of an attribute. Like the list of names, it's retried when it
+    /// changes between getting its size and reading it.
+    pub fn value(path: &Path, name: &str) -> Option<Vec<u8>> {
+        let path = CString::new(path.as_os_str().as_bytes()).ok()?;
+        let name = CString::new(name).ok()?;
+
+        loop {
+            // SAFETY: a null buffer with a size of 0 only asks for the size.
+            let size = unsafe { read_value(&path, &name, ptr::null_mut(), 0) };
+            let mut buffer = vec![0u8; usize::try_from(size).ok()?];
+
+            // SAFETY: the buffer is as large as the size passed along with it.
+            let size =
+                unsafe { read_value(&path, &name, buffer.as_mut_ptr().cast(), buffer.len()) };
+
+            if size >= 0 {
+                buffer.truncate(size as usize);
+                return Some(buffer);
+            }
+
+            if std::io::Error::last_os_error().raw_os_error() != Some(libc::ERANGE) {
+                return None;
+            }
+        }
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    unsafe fn list_names(path: &CString, buffer: *mut libc::c_char, size: usize) -> isize {
+        libc::llistxattr(path.as_ptr(), buffer, size)
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    unsafe fn read_value(
+        path: &CString,
+        name: &CString,
+        buffer: *mut libc::c_void,
+        size: usize,
+    ) -> isize {
+        libc::lgetxattr(path.as_ptr(), name.as_ptr(), buffer, size)
+    }
+
+    #[cfg(target_os = "macos")]
+    unsafe fn list_names(path: &CString, buffer: *mut libc::c_char, size: usize) -> isize {
+        libc::listxattr(path.as_ptr(), buffer, size, libc::XATTR_NOFOLLOW)
+    }
+
+    #[cfg(target_os = "macos")]
+    unsafe fn read_value(
+        path: &CString,
+        name: &CString,
+        buffer: *mut libc::c_void,
+        size: usize,
+    ) -> isize {
+        libc::getxattr(
+            path.as_ptr(),
+            name.as_ptr(),
+            buffer,
+            size,
+            0,
+            libc::XATTR_NOFOLLOW,
+        )
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android", target_os = "macos")))]
+mod sys {
+    use std::path::Path;
+
+    pub fn list(_path: &Path) -> Option<Vec<u8>> {
+        None
+    }
+
+    pub fn size(_path: &Path, _name: &str) -> Option<usize> {
+        None
+    }
+
+    pub fn value(_path: &Path, _name: &str) -> Option<Vec<u8>> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ignores_security_attributes() {
+        assert!(!has_non_security_names(b""));
+        assert!(!has_non_security_names(b"security.selinux\0"));
+        assert!(!has_non_security_names(
+            b"security.selinux\0security.capability\0"
+        ));
+        assert!(has_non_security_names(b"user.comment\0"));
+        assert!(has_non_security_names(
+            b"security.selinux\0com.apple.quarantine\0"
+        ));
+    }
+}